@@ -1,16 +1,19 @@
-pub const VERSION: &'static str = "v3";
-pub const AUTH: &'static str = "auth";
-pub const REQUEST: &'static str = "r";
-pub const WALLETS: &'static str = "wallets";
-pub const BOOK: &'static str = "book";
-pub const W: &'static str = "w";
-pub const ORDER: &'static str = "order";
-pub const ORDERS: &'static str = "orders";
-pub const SUBMIT: &'static str = "submit";
-pub const MARKETS: &'static str = "markets";
-pub const CANCEL: &'static str = "cancel";
-
-#[derive(Clone, Copy, Debug)]
+pub const VERSION: &str = "v3";
+pub const AUTH: &str = "auth";
+pub const REQUEST: &str = "r";
+pub const WALLETS: &str = "wallets";
+pub const BOOK: &str = "book";
+pub const W: &str = "w";
+pub const ORDER: &str = "order";
+pub const ORDERS: &str = "orders";
+pub const SUBMIT: &str = "submit";
+pub const MARKETS: &str = "markets";
+pub const CANCEL: &str = "cancel";
+pub const HISTORY: &str = "hist";
+
+/// Serialized as "buy" or "sell".
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
 pub enum Side {
     Sell,
     Buy,
@@ -28,7 +31,9 @@ impl std::convert::TryFrom<&str> for Side {
     }
 }
 
-#[derive(Clone, Copy, Debug)]
+/// Serialized as "market" or "limit".
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
 pub enum Target {
     Market,
     Limit,
@@ -55,28 +60,120 @@ impl std::fmt::Display for Target {
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Endpoint {
+    OrderBook,
+    Markets,
+    Balance,
+    CreateOrder,
+    CancelOrder,
+    MyOrders,
+    OrderHistory,
+}
+
+impl Endpoint {
+    pub fn is_private(&self) -> bool {
+        match self {
+            Endpoint::OrderBook | Endpoint::Markets => false,
+            Endpoint::Balance
+            | Endpoint::CreateOrder
+            | Endpoint::CancelOrder
+            | Endpoint::MyOrders
+            | Endpoint::OrderHistory => true,
+        }
+    }
+
+    pub fn is_idempotent(&self) -> bool {
+        match self {
+            Endpoint::OrderBook
+            | Endpoint::Markets
+            | Endpoint::Balance
+            | Endpoint::MyOrders
+            | Endpoint::OrderHistory => true,
+            Endpoint::CreateOrder | Endpoint::CancelOrder => false,
+        }
+    }
+}
+
+impl std::fmt::Display for Endpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Endpoint::OrderBook => write!(f, "get_orderbook"),
+            Endpoint::Markets => write!(f, "get_markets"),
+            Endpoint::Balance => write!(f, "get_balance"),
+            Endpoint::CreateOrder => write!(f, "create_order"),
+            Endpoint::CancelOrder => write!(f, "delete_order"),
+            Endpoint::MyOrders => write!(f, "get_my_orders"),
+            Endpoint::OrderHistory => write!(f, "get_order_history"),
+        }
+    }
+}
+
+pub const REDACTED: &str = "[REDACTED]";
+pub const SENSITIVE_HEADERS: [&str; 2] = ["kun-apikey", "kun-signature"];
+
+pub fn is_sensitive_header(name: &http::header::HeaderName) -> bool {
+    SENSITIVE_HEADERS.contains(&name.as_str())
+}
+
+/// Formats headers for logs and errors with the values of `SENSITIVE_HEADERS` replaced.
+pub struct RedactedHeaders<'a>(pub &'a http::HeaderMap);
+
+impl std::fmt::Debug for RedactedHeaders<'_> {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        formatter
+            .debug_map()
+            .entries(self.0.iter().map(|(name, value)| {
+                let value = if is_sensitive_header(name) {
+                    REDACTED.to_owned()
+                } else {
+                    String::from_utf8_lossy(value.as_bytes()).into_owned()
+                };
+                (name.as_str(), value)
+            }))
+            .finish()
+    }
+}
+
 pub fn default_request_builder(url: &url::Url) -> http::request::Builder {
     http::Request::builder()
         .header("Accept", "application/json")
         .uri(url.to_string())
 }
 
-pub fn sign_request(
-    builder: http::request::Builder,
-    url: &url::Url,
-    body_json: Option<&str>,
+/// The message covered by `kun-signature`, an empty body is signed as `{}`.
+pub fn signing_message(path: &str, nonce: i64, body: &[u8]) -> String {
+    let body = if body.is_empty() {
+        std::borrow::Cow::Borrowed("{}")
+    } else {
+        String::from_utf8_lossy(body)
+    };
+    format!("{}{}{}", path, nonce, body)
+}
+
+/// Adds the nonce, api key and signature headers, signing the request path and body
+/// with the signer of `auth`.
+pub async fn sign_request(
+    mut request: crate::transport::Request,
     auth: &crate::context::AuthContext
-) -> http::request::Builder {
-    let timestamp = chrono::Utc::now().timestamp_millis();
-    let body = body_json.unwrap_or("{}");
-    let message = format!("{}{}{}", url.path(), timestamp, body);
-    builder
-        .header("Content-Type", "application/json")
-        .header("kun-nonce", timestamp)
-        .header("kun-apikey", &auth.public_key)
-        .header("kun-signature", auth.sign(&message))
+) -> Result<crate::transport::Request, String> {
+    let nonce = chrono::Utc::now().timestamp_millis();
+    let message = signing_message(request.uri().path(), nonce, request.body());
+    let signature = auth.sign(&message).await?;
+    let header = |value: &str| {
+        http::HeaderValue::from_str(value).map_err(|error| format!("Invalid header value: {:#?}", error))
+    };
+    let headers = request.headers_mut();
+    headers
+        .entry(http::header::CONTENT_TYPE)
+        .or_insert(http::HeaderValue::from_static("application/json"));
+    headers.insert("kun-nonce", http::HeaderValue::from(nonce));
+    headers.insert("kun-apikey", header(&auth.public_key)?);
+    headers.insert("kun-signature", header(&signature)?);
+    Ok(request)
 }
 
+#[cfg(feature = "hyper")]
 pub fn create_request_with_body(
     builder: http::request::Builder,
     body: hyper::Body
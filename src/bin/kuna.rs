@@ -0,0 +1,377 @@
+use kuna_sdk::builder::KunaClientBuilder;
+use kuna_sdk::client::KunaClient;
+use kuna_sdk::coin::Coins;
+use kuna_sdk::credentials::Credentials;
+use kuna_sdk::public::KunaPublicClient;
+use std::convert::TryFrom;
+
+const USAGE: &str = "Usage: kuna [--json] [--config <path>] [--base-url <url>] <command>
+
+Commands:
+    markets                                  List markets
+    book <symbol> [--depth <n>]              Show the order book, 10 levels per side by default
+    balance                                  Show balances
+    orders <symbol>                          List open orders
+    place <symbol> <buy|sell> <amount> (--price <price> | --market)
+                                             Place a limit order at the price or a market order
+    cancel <order id>                        Cancel an order
+    cancel-all <symbol>                      Cancel every open order of a market
+
+Credentials are read from KUNA_PUBLIC_KEY and KUNA_PRIVATE_KEY, or from a json config file
+with `public_key`, `private_key` and optionally `base_url` (default ~/.config/kuna/config.json).
+The config file must only be readable by its owner.";
+
+// Only tells whether the keys are present, they are parsed by `Credentials` from `contents`.
+#[derive(serde::Deserialize)]
+struct ConfigFields {
+    public_key: Option<serde::de::IgnoredAny>,
+    private_key: Option<serde::de::IgnoredAny>,
+    base_url: Option<String>,
+}
+
+#[derive(Default)]
+struct Config {
+    path: Option<std::path::PathBuf>,
+    contents: Option<zeroize::Zeroizing<Vec<u8>>>,
+    has_keys: bool,
+    base_url: Option<String>,
+}
+
+struct Options {
+    json: bool,
+    config: Option<std::path::PathBuf>,
+    base_url: Option<String>,
+    depth: usize,
+    price: Option<f64>,
+    market: bool,
+    help: bool,
+    arguments: Vec<String>,
+}
+
+fn parse_options(mut arguments: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options {
+        json: false,
+        config: None,
+        base_url: None,
+        depth: 10,
+        price: None,
+        market: false,
+        help: false,
+        arguments: Vec::new(),
+    };
+    while let Some(argument) = arguments.next() {
+        let mut value = |name: &str| match arguments.next() {
+            Some(value) => Ok(value),
+            None => Err(format!("Missing value for {}", name)),
+        };
+        match argument.as_ref() {
+            "--json" => options.json = true,
+            "--config" => options.config = Some(value("--config")?.into()),
+            "--base-url" => options.base_url = Some(value("--base-url")?),
+            "--depth" => options.depth = parse_number("--depth", &value("--depth")?)?,
+            "--price" => options.price = Some(parse_number("--price", &value("--price")?)?),
+            "--market" => options.market = true,
+            "-h" | "--help" => options.help = true,
+            _ if argument.starts_with("--") => return Err(format!("Unknown option {}\n\n{}", argument, USAGE)),
+            _ => options.arguments.push(argument),
+        }
+    }
+    Ok(options)
+}
+
+fn parse_number<T: std::str::FromStr>(name: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("Invalid value for {}: {}", name, value))
+}
+
+fn parse_coins(symbol: Option<&String>) -> Result<Coins, String> {
+    match symbol {
+        Some(symbol) => Coins::try_from(symbol.to_lowercase().as_ref()).map_err(|error| format!("{}: {}", error, symbol)),
+        None => Err(format!("Missing market symbol\n\n{}", USAGE)),
+    }
+}
+
+fn load_config(options: &Options) -> Result<Config, String> {
+    let path = match &options.config {
+        Some(path) => path.clone(),
+        None => match std::env::var_os("HOME") {
+            Some(home) => std::path::PathBuf::from(home).join(".config/kuna/config.json"),
+            None => return Ok(Config::default()),
+        },
+    };
+    if options.config.is_none() && !path.exists() {
+        return Ok(Config::default());
+    }
+    let contents = kuna_sdk::credentials::read_protected_file(&path)?;
+    match serde_json::from_slice::<ConfigFields>(&contents) {
+        Ok(fields) => Ok(Config {
+            path: Some(path),
+            contents: Some(contents),
+            has_keys: fields.public_key.is_some() || fields.private_key.is_some(),
+            base_url: fields.base_url,
+        }),
+        Err(error) => Err(format!("Invalid config {:?}: {}", path, error)),
+    }
+}
+
+fn builder(options: &Options, config: &Config) -> Result<KunaClientBuilder, String> {
+    let builder = KunaClientBuilder::production();
+    let builder = match options.base_url.as_ref().or(config.base_url.as_ref()) {
+        Some(base_url) => match url::Url::parse(base_url) {
+            Ok(base_url) => builder.base_url(base_url),
+            Err(error) => return Err(format!("Invalid base url {}: {}", base_url, error)),
+        },
+        None => builder,
+    };
+    Ok(builder)
+}
+
+// The environment wins over the config file as soon as one of the variables is set.
+fn credentials(config: &Config) -> Result<Credentials, String> {
+    let is_set = |name: &str| std::env::var_os(name).is_some_and(|value| !value.is_empty());
+    if is_set(kuna_sdk::credentials::PUBLIC_KEY_VAR) || is_set(kuna_sdk::credentials::PRIVATE_KEY_VAR) {
+        return Ok(Credentials::from_env()?);
+    }
+    match (&config.path, &config.contents) {
+        (Some(path), Some(contents)) if config.has_keys => Ok(Credentials::from_file_contents(path, contents)?),
+        _ => Err("No credentials, set KUNA_PUBLIC_KEY and KUNA_PRIVATE_KEY or use a config file".to_owned()),
+    }
+}
+
+fn private_client(builder: KunaClientBuilder, config: &Config) -> Result<KunaClient, String> {
+    builder.with_credentials(&credentials(config)?).build_private()
+}
+
+fn print_json<T: serde::Serialize>(value: &T) -> Result<(), String> {
+    match serde_json::to_string_pretty(value) {
+        Ok(json) => {
+            println!("{}", json);
+            Ok(())
+        },
+        Err(error) => Err(format!("Failed to serialize output: {}", error)),
+    }
+}
+
+fn print_table(headers: &[&str], rows: Vec<Vec<String>>) {
+    let widths: Vec<usize> = headers
+        .iter()
+        .enumerate()
+        .map(|(column, header)| {
+            rows.iter()
+                .map(|row| row[column].len())
+                .chain(std::iter::once(header.len()))
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+    let format_row = |row: Vec<String>| {
+        row.iter()
+            .zip(widths.iter())
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect::<Vec<_>>()
+            .join("  ")
+            .trim_end()
+            .to_owned()
+    };
+    println!("{}", format_row(headers.iter().map(|header| header.to_string()).collect()));
+    for row in rows {
+        println!("{}", format_row(row));
+    }
+}
+
+fn optional(value: Option<f64>) -> String {
+    match value {
+        Some(value) => value.to_string(),
+        None => "-".to_owned(),
+    }
+}
+
+fn print_orders(options: &Options, orders: &[kuna_sdk::order::Order]) -> Result<(), String> {
+    if options.json {
+        return print_json(&orders);
+    }
+    print_table(
+        &["ID", "MARKET", "SIDE", "TYPE", "STATUS", "PRICE", "AMOUNT", "EXECUTED", "AVG PRICE"],
+        orders
+            .iter()
+            .map(|order| {
+                vec![
+                    order.id.to_string(),
+                    order.coins.to_string(),
+                    format!("{:?}", order.side).to_lowercase(),
+                    order.target.to_string(),
+                    order.status.to_string(),
+                    optional(order.price),
+                    order.original_amount.to_string(),
+                    order.executed_amount.to_string(),
+                    optional(order.average_price),
+                ]
+            })
+            .collect(),
+    );
+    Ok(())
+}
+
+async fn run(options: Options) -> Result<(), String> {
+    let config = load_config(&options)?;
+    let builder = builder(&options, &config)?;
+    let command = match options.arguments.first() {
+        Some(command) => command.as_str(),
+        None => return Err(USAGE.to_owned()),
+    };
+    let arguments = &options.arguments[1..];
+    match command {
+        "markets" => {
+            let public_client: KunaPublicClient = builder.build_public()?;
+            let markets = public_client.get_markets().await?;
+            if options.json {
+                return print_json(&markets);
+            }
+            print_table(
+                &["MARKET", "BASE", "QUOTE", "BASE PRECISION", "QUOTE PRECISION", "CHANGE"],
+                markets
+                    .iter()
+                    .map(|market| {
+                        vec![
+                            market.id.clone(),
+                            market.base_unit.clone(),
+                            market.quote_unit.clone(),
+                            market.base_precision.to_string(),
+                            market.quote_precision.to_string(),
+                            market.price_change.to_string(),
+                        ]
+                    })
+                    .collect(),
+            );
+            Ok(())
+        },
+        "book" => {
+            let coins = parse_coins(arguments.first())?;
+            let book = builder.build_public()?.get_orderbook(coins).await?;
+            if options.json {
+                return print_json(&book);
+            }
+            let mut asks: Vec<_> = book.sorted_asks().into_iter().take(options.depth).collect();
+            asks.reverse();
+            let bids = book.sorted_bids().into_iter().take(options.depth);
+            let row = |side: &str, level: &kuna_sdk::models::OrderBookItem| {
+                vec![
+                    side.to_owned(),
+                    level.price.to_string(),
+                    level.amount.to_string(),
+                    level.orders_number.to_string(),
+                ]
+            };
+            print_table(
+                &["SIDE", "PRICE", "AMOUNT", "ORDERS"],
+                asks.into_iter()
+                    .map(|level| row("ask", level))
+                    .chain(bids.map(|level| row("bid", level)))
+                    .collect(),
+            );
+            Ok(())
+        },
+        "balance" => {
+            let balances = private_client(builder, &config)?.get_balance().await?;
+            if options.json {
+                return print_json(&balances);
+            }
+            print_table(
+                &["COIN", "TOTAL", "AVAILABLE"],
+                balances
+                    .iter()
+                    .map(|currency| {
+                        vec![
+                            currency.coin.to_string(),
+                            currency.full.to_string(),
+                            currency.available.to_string(),
+                        ]
+                    })
+                    .collect(),
+            );
+            Ok(())
+        },
+        "orders" => {
+            let coins = parse_coins(arguments.first())?;
+            let orders = private_client(builder, &config)?.get_my_orders(coins).await?;
+            print_orders(&options, &orders)
+        },
+        "place" => {
+            let coins = parse_coins(arguments.first())?;
+            let side = match arguments.get(1).map(|side| side.to_lowercase()) {
+                Some(side) => kuna_sdk::base::Side::try_from(side.as_ref()).map_err(|error| error.to_owned())?,
+                None => return Err(format!("Missing side\n\n{}", USAGE)),
+            };
+            let amount = match arguments.get(2) {
+                Some(amount) => parse_number("amount", amount)?,
+                None => return Err(format!("Missing amount\n\n{}", USAGE)),
+            };
+            let target = match (options.price, options.market) {
+                (Some(_), false) => kuna_sdk::base::Target::Limit,
+                (None, true) => kuna_sdk::base::Target::Market,
+                (Some(_), true) => return Err("--price and --market can not be combined".to_owned()),
+                (None, false) => return Err(format!("Either --price or --market is required\n\n{}", USAGE)),
+            };
+            let order = kuna_sdk::models::CreateOrder::new(coins, side, target, amount, options.price.unwrap_or(0.0));
+            let order = private_client(builder, &config)?.create_order(order).await?;
+            print_orders(&options, &[order])
+        },
+        "cancel" => {
+            let order_id = match arguments.first() {
+                Some(order_id) => parse_number("order id", order_id)?,
+                None => return Err(format!("Missing order id\n\n{}", USAGE)),
+            };
+            let order = private_client(builder, &config)?
+                .delete_order(kuna_sdk::models::CancelOrderRequest { order_id })
+                .await?;
+            print_orders(&options, &[order])
+        },
+        "cancel-all" => {
+            let coins = parse_coins(arguments.first())?;
+            let client = private_client(builder, &config)?;
+            let mut canceled = Vec::new();
+            let mut failures = Vec::new();
+            for order in client.get_my_orders(coins).await? {
+                match client
+                    .delete_order(kuna_sdk::models::CancelOrderRequest { order_id: order.id })
+                    .await
+                {
+                    Ok(order) => canceled.push(order),
+                    Err(error) => failures.push(format!("Failed to cancel {}: {}", order.id, error)),
+                }
+            }
+            print_orders(&options, &canceled)?;
+            match failures.is_empty() {
+                true => Ok(()),
+                false => Err(failures.join("\n")),
+            }
+        },
+        _ => Err(format!("Unknown command {}\n\n{}", command, USAGE)),
+    }
+}
+
+fn main() {
+    let options = match parse_options(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(error) => {
+            eprintln!("{}", error);
+            std::process::exit(2);
+        },
+    };
+    if options.help {
+        println!("{}", USAGE);
+        return;
+    }
+    let runtime = match tokio::runtime::Builder::new_current_thread().enable_all().build() {
+        Ok(runtime) => runtime,
+        Err(error) => {
+            eprintln!("Failed to start runtime: {}", error);
+            std::process::exit(1);
+        },
+    };
+    if let Err(error) = runtime.block_on(run(options)) {
+        eprintln!("{}", error);
+        std::process::exit(1);
+    }
+}
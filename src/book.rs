@@ -0,0 +1,513 @@
+use crate::base::Side;
+use crate::models::OrderBook;
+use crate::models::OrderBookItem;
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+pub struct MarketOrderEstimate {
+    pub side: Side,
+    pub requested_amount: f64,
+    pub filled_amount: f64,
+    pub average_price: f64,
+    pub best_price: f64,
+    pub worst_price: f64,
+    pub slippage: f64,
+    pub slippage_bps: f64,
+}
+
+impl MarketOrderEstimate {
+    pub fn is_fully_filled(&self) -> bool {
+        self.filled_amount >= self.requested_amount
+    }
+}
+
+fn compare_prices(left: f64, right: f64) -> std::cmp::Ordering {
+    left.partial_cmp(&right).unwrap_or(std::cmp::Ordering::Equal)
+}
+
+/// In the analytics below `side` is always the side of the order being placed:
+/// a buy executes against the asks and a sell against the bids.
+impl OrderBook {
+    pub fn sorted_bids(&self) -> Vec<&OrderBookItem> {
+        let mut bids: Vec<_> = self.bids.iter().collect();
+        bids.sort_by(|left, right| compare_prices(right.price, left.price));
+        bids
+    }
+
+    pub fn sorted_asks(&self) -> Vec<&OrderBookItem> {
+        let mut asks: Vec<_> = self.asks.iter().collect();
+        asks.sort_by(|left, right| compare_prices(left.price, right.price));
+        asks
+    }
+
+    /// The levels an order on `side` executes against, best price first.
+    pub fn levels(&self, side: Side) -> Vec<&OrderBookItem> {
+        match side {
+            Side::Buy => self.sorted_asks(),
+            Side::Sell => self.sorted_bids(),
+        }
+    }
+
+    pub fn best_bid(&self) -> Option<&OrderBookItem> {
+        self.bids
+            .iter()
+            .max_by(|left, right| compare_prices(left.price, right.price))
+    }
+
+    pub fn best_ask(&self) -> Option<&OrderBookItem> {
+        self.asks
+            .iter()
+            .min_by(|left, right| compare_prices(left.price, right.price))
+    }
+
+    pub fn spread(&self) -> Option<f64> {
+        match (self.best_bid(), self.best_ask()) {
+            (Some(bid), Some(ask)) => Some(ask.price - bid.price),
+            _ => None,
+        }
+    }
+
+    pub fn spread_bps(&self) -> Option<f64> {
+        match (self.spread(), self.mid_price()) {
+            (Some(spread), Some(mid)) if mid > 0.0 => Some(spread / mid * 10_000.0),
+            _ => None,
+        }
+    }
+
+    pub fn mid_price(&self) -> Option<f64> {
+        match (self.best_bid(), self.best_ask()) {
+            (Some(bid), Some(ask)) => Some((bid.price + ask.price) / 2.0),
+            _ => None,
+        }
+    }
+
+    pub fn microprice(&self) -> Option<f64> {
+        let (bid, ask) = match (self.best_bid(), self.best_ask()) {
+            (Some(bid), Some(ask)) => (bid, ask),
+            _ => return None,
+        };
+        let total = bid.amount + ask.amount;
+        if total <= 0.0 {
+            return self.mid_price();
+        }
+        Some((bid.price * ask.amount + ask.price * bid.amount) / total)
+    }
+
+    /// Amount an order on `side` can fill at `price` or better,
+    /// i.e. asks at or below `price` for a buy and bids at or above it for a sell.
+    pub fn depth_to(&self, side: Side, price: f64) -> f64 {
+        self.levels(side)
+            .into_iter()
+            .take_while(|level| match side {
+                Side::Buy => level.price <= price,
+                Side::Sell => level.price >= price,
+            })
+            .map(|level| level.amount)
+            .sum()
+    }
+
+    /// Volume weighted price of filling `amount` with an order on `side`.
+    /// Returns `None` when the book is not deep enough, unlike `average_fill_price`
+    /// which averages over the part that can be filled.
+    pub fn vwap(&self, side: Side, amount: f64) -> Option<f64> {
+        if amount <= 0.0 {
+            return None;
+        }
+        let (filled, notional) = Self::consume(self.levels(side), amount);
+        if filled < amount {
+            return None;
+        }
+        Some(notional / filled)
+    }
+
+    /// Estimates the execution of a market order of `amount` placed on `side`,
+    /// i.e. a buy consumes the asks and a sell consumes the bids.
+    pub fn estimate_market_order(&self, side: Side, amount: f64) -> Option<MarketOrderEstimate> {
+        if amount <= 0.0 {
+            return None;
+        }
+        let levels = self.levels(side);
+        let best_price = levels.first()?.price;
+        let worst_price = levels
+            .iter()
+            .scan(0.0, |consumed, level| {
+                if *consumed >= amount {
+                    return None;
+                }
+                *consumed += level.amount;
+                Some(level.price)
+            })
+            .last()
+            .unwrap_or(best_price);
+        let (filled_amount, notional) = Self::consume(levels, amount);
+        if filled_amount <= 0.0 {
+            return None;
+        }
+        let average_price = notional / filled_amount;
+        let slippage = match side {
+            Side::Buy => average_price - best_price,
+            Side::Sell => best_price - average_price,
+        };
+        Some(MarketOrderEstimate {
+            side,
+            requested_amount: amount,
+            filled_amount,
+            average_price,
+            best_price,
+            worst_price,
+            slippage,
+            slippage_bps: if best_price > 0.0 { slippage / best_price * 10_000.0 } else { 0.0 },
+        })
+    }
+
+    pub fn average_fill_price(&self, side: Side, amount: f64) -> Option<f64> {
+        self.estimate_market_order(side, amount)
+            .map(|estimate| estimate.average_price)
+    }
+
+    fn consume(levels: Vec<&OrderBookItem>, amount: f64) -> (f64, f64) {
+        levels
+            .into_iter()
+            .fold((0.0, 0.0), |(filled, notional), level| {
+                let take = (amount - filled).min(level.amount).max(0.0);
+                (filled + take, notional + take * level.price)
+            })
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LevelChangeKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+pub struct LevelChange {
+    // The side of the orders resting at the level, `Buy` for bids and `Sell` for asks.
+    pub side: Side,
+    pub kind: LevelChangeKind,
+    pub price: f64,
+    pub old_amount: f64,
+    pub new_amount: f64,
+    pub orders_number_delta: i64,
+}
+
+impl LevelChange {
+    pub fn amount_delta(&self) -> f64 {
+        self.new_amount - self.old_amount
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, Default)]
+pub struct OrderBookDiff {
+    pub changes: Vec<LevelChange>,
+}
+
+impl OrderBookDiff {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+fn diff_levels(
+    side: Side,
+    old_levels: &[OrderBookItem],
+    new_levels: &[OrderBookItem],
+    changes: &mut Vec<LevelChange>,
+) {
+    for old in old_levels {
+        match new_levels.iter().find(|new| new.price == old.price) {
+            Some(new) => {
+                if new.amount != old.amount || new.orders_number != old.orders_number {
+                    changes.push(LevelChange {
+                        side,
+                        kind: LevelChangeKind::Changed,
+                        price: old.price,
+                        old_amount: old.amount,
+                        new_amount: new.amount,
+                        orders_number_delta: new.orders_number as i64 - old.orders_number as i64,
+                    });
+                }
+            },
+            None => changes.push(LevelChange {
+                side,
+                kind: LevelChangeKind::Removed,
+                price: old.price,
+                old_amount: old.amount,
+                new_amount: 0.0,
+                orders_number_delta: -(old.orders_number as i64),
+            }),
+        }
+    }
+    for new in new_levels {
+        if !old_levels.iter().any(|old| old.price == new.price) {
+            changes.push(LevelChange {
+                side,
+                kind: LevelChangeKind::Added,
+                price: new.price,
+                old_amount: 0.0,
+                new_amount: new.amount,
+                orders_number_delta: new.orders_number as i64,
+            });
+        }
+    }
+}
+
+fn apply_level_change(levels: &mut Vec<OrderBookItem>, change: &LevelChange) {
+    let position = levels.iter().position(|level| level.price == change.price);
+    match (change.kind, position) {
+        (LevelChangeKind::Removed, Some(position)) => {
+            levels.remove(position);
+        },
+        (LevelChangeKind::Changed, Some(position)) | (LevelChangeKind::Added, Some(position)) => {
+            let level = &mut levels[position];
+            level.amount = change.new_amount;
+            level.orders_number = (level.orders_number as i64 + change.orders_number_delta).max(0) as u32;
+        },
+        (LevelChangeKind::Removed, None) => (),
+        (_, None) => levels.push(OrderBookItem {
+            price: change.price,
+            amount: change.new_amount,
+            orders_number: change.orders_number_delta.max(0) as u32,
+        }),
+    }
+}
+
+impl OrderBook {
+    /// Level by level changes required to turn `self` into `other`. Levels are matched by
+    /// exact price equality, which holds for prices as the exchange reports them; aggregate
+    /// both books with the same tick first when prices were computed.
+    pub fn diff(&self, other: &OrderBook) -> OrderBookDiff {
+        let mut changes = Vec::new();
+        diff_levels(Side::Buy, &self.bids, &other.bids, &mut changes);
+        diff_levels(Side::Sell, &self.asks, &other.asks, &mut changes);
+        OrderBookDiff { changes }
+    }
+
+    pub fn apply(&mut self, diff: &OrderBookDiff) {
+        for change in diff.changes.iter() {
+            match change.side {
+                Side::Buy => apply_level_change(&mut self.bids, change),
+                Side::Sell => apply_level_change(&mut self.asks, change),
+            }
+        }
+        self.bids.sort_by(|left, right| compare_prices(right.price, left.price));
+        self.asks.sort_by(|left, right| compare_prices(left.price, right.price));
+    }
+
+    pub fn applied(&self, diff: &OrderBookDiff) -> OrderBook {
+        let mut order_book = self.clone();
+        order_book.apply(diff);
+        order_book
+    }
+}
+
+const TICK_EPSILON: f64 = 1e-9;
+
+fn aggregate_levels(
+    levels: Vec<&OrderBookItem>,
+    tick: f64,
+    round_up: bool,
+    depth: Option<usize>,
+) -> Vec<OrderBookItem> {
+    let mut buckets: Vec<OrderBookItem> = Vec::new();
+    for level in levels {
+        let ticks = level.price / tick;
+        let ticks = if round_up {
+            (ticks - TICK_EPSILON).ceil()
+        } else {
+            (ticks + TICK_EPSILON).floor()
+        };
+        let price = ticks * tick;
+        match buckets.last_mut() {
+            Some(bucket) if (bucket.price - price).abs() < tick / 2.0 => {
+                bucket.amount += level.amount;
+                bucket.orders_number += level.orders_number;
+            },
+            _ => {
+                if depth.is_some_and(|depth| buckets.len() >= depth) {
+                    break;
+                }
+                buckets.push(OrderBookItem {
+                    price,
+                    amount: level.amount,
+                    orders_number: level.orders_number,
+                });
+            },
+        }
+    }
+    buckets
+}
+
+impl OrderBook {
+    /// Groups price levels into buckets of `tick` size, bids are rounded down and asks up.
+    /// `depth` limits the number of buckets kept on each side. `tick` must be finite and positive.
+    pub fn aggregate(&self, tick: f64, depth: Option<usize>) -> Result<OrderBook, String> {
+        if !(tick.is_finite() && tick > 0.0) {
+            return Err(format!("Invalid tick {}, expected a positive number", tick));
+        }
+        Ok(OrderBook {
+            coins: self.coins.clone(),
+            bids: aggregate_levels(self.sorted_bids(), tick, false, depth),
+            asks: aggregate_levels(self.sorted_asks(), tick, true, depth),
+        })
+    }
+
+    /// `aggregate` with the price tick of `market`, derived from its quote precision.
+    pub fn aggregate_for_market(
+        &self,
+        market: &crate::models::Market,
+        depth: Option<usize>,
+    ) -> Result<OrderBook, String> {
+        self.aggregate(market.price_tick(), depth)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coin::Coins;
+
+    fn level(price: f64, amount: f64) -> OrderBookItem {
+        OrderBookItem {
+            price,
+            amount,
+            orders_number: 1,
+        }
+    }
+
+    fn book() -> OrderBook {
+        OrderBook {
+            coins: Coins::TonUsdt,
+            bids: vec![level(1.9, 2.0), level(2.0, 1.0)],
+            asks: vec![level(2.2, 2.0), level(2.1, 1.0)],
+        }
+    }
+
+    #[test]
+    fn side_is_the_side_of_the_order_being_placed() {
+        let book = book();
+        assert_eq!(book.levels(Side::Buy)[0].price, 2.1);
+        assert_eq!(book.levels(Side::Sell)[0].price, 2.0);
+        assert_eq!(book.depth_to(Side::Buy, 2.1), 1.0);
+        assert_eq!(book.depth_to(Side::Sell, 1.9), 3.0);
+        for side in [Side::Buy, Side::Sell] {
+            assert_eq!(book.vwap(side, 2.0), book.average_fill_price(side, 2.0));
+        }
+        assert_eq!(book.vwap(Side::Buy, 2.0), Some((2.1 + 2.2) / 2.0));
+        assert_eq!(book.vwap(Side::Buy, 4.0), None);
+    }
+
+    fn levels_of(levels: &[OrderBookItem]) -> Vec<(f64, f64, u32)> {
+        levels.iter().map(|level| (level.price, level.amount, level.orders_number)).collect()
+    }
+
+    #[test]
+    fn applying_a_diff_reproduces_the_new_book() {
+        let old = OrderBook {
+            coins: Coins::TonUsdt,
+            bids: vec![level(2.0, 1.0), level(1.9, 2.0)],
+            asks: vec![level(2.1, 1.0), level(2.2, 3.0)],
+        };
+        let new = OrderBook {
+            coins: Coins::TonUsdt,
+            bids: vec![
+                OrderBookItem {
+                    price: 2.0,
+                    amount: 1.5,
+                    orders_number: 3,
+                },
+                level(1.8, 4.0),
+            ],
+            asks: vec![
+                level(2.1, 1.0),
+                OrderBookItem {
+                    price: 2.2,
+                    amount: 3.0,
+                    orders_number: 2,
+                },
+                level(2.3, 0.5),
+            ],
+        };
+        let diff = old.diff(&new);
+        let changes: Vec<_> = diff
+            .changes
+            .iter()
+            .map(|change| (change.side, change.kind, change.price, change.amount_delta(), change.orders_number_delta))
+            .collect();
+        assert_eq!(
+            changes,
+            vec![
+                (Side::Buy, LevelChangeKind::Changed, 2.0, 0.5, 2),
+                (Side::Buy, LevelChangeKind::Removed, 1.9, -2.0, -1),
+                (Side::Buy, LevelChangeKind::Added, 1.8, 4.0, 1),
+                (Side::Sell, LevelChangeKind::Changed, 2.2, 0.0, 1),
+                (Side::Sell, LevelChangeKind::Added, 2.3, 0.5, 1),
+            ]
+        );
+
+        let applied = old.applied(&diff);
+        assert_eq!(levels_of(&applied.bids), levels_of(&new.bids));
+        assert_eq!(levels_of(&applied.asks), levels_of(&new.asks));
+        assert!(applied.diff(&new).is_empty());
+        assert!(new.diff(&new).is_empty());
+        // Diffing back undoes it.
+        assert_eq!(levels_of(&new.applied(&new.diff(&old)).bids), levels_of(&old.bids));
+        assert_eq!(levels_of(&new.applied(&new.diff(&old)).asks), levels_of(&old.asks));
+    }
+
+    // Bucket prices are multiples of the tick, compared with a tolerance.
+    fn assert_levels(levels: &[OrderBookItem], expected: &[(f64, f64, u32)]) {
+        assert_eq!(levels.len(), expected.len(), "{:?}", levels_of(levels));
+        for (level, (price, amount, orders_number)) in levels.iter().zip(expected.iter()) {
+            assert!((level.price - price).abs() < 1e-9, "{:?}", levels_of(levels));
+            assert_eq!((level.amount, level.orders_number), (*amount, *orders_number));
+        }
+    }
+
+    #[test]
+    fn aggregation_rounds_bids_down_and_asks_up_and_merges_levels() {
+        let book = OrderBook {
+            coins: Coins::TonUsdt,
+            bids: vec![level(2.04, 1.0), level(2.09, 2.0), level(1.95, 0.5), level(2.1, 1.0), level(1.8, 1.0)],
+            asks: vec![level(2.11, 1.0), level(2.19, 2.0), level(2.2, 1.0), level(2.31, 0.5)],
+        };
+        let aggregated = book.aggregate(0.1, None).expect("Invalid tick");
+        assert_levels(&aggregated.bids, &[(2.1, 1.0, 1), (2.0, 3.0, 2), (1.9, 0.5, 1), (1.8, 1.0, 1)]);
+        assert_levels(&aggregated.asks, &[(2.2, 4.0, 3), (2.4, 0.5, 1)]);
+
+        let truncated = book.aggregate(0.1, Some(2)).expect("Invalid tick");
+        assert_levels(&truncated.bids, &[(2.1, 1.0, 1), (2.0, 3.0, 2)]);
+        assert_levels(&truncated.asks, &[(2.2, 4.0, 3), (2.4, 0.5, 1)]);
+        assert!(book.aggregate(0.1, Some(0)).expect("Invalid tick").bids.is_empty());
+    }
+
+    #[test]
+    fn aggregation_rejects_invalid_ticks() {
+        for tick in [0.0, -0.1, f64::NAN, f64::INFINITY] {
+            assert!(book().aggregate(tick, None).is_err(), "{}", tick);
+        }
+    }
+
+    #[test]
+    fn aggregation_for_market_uses_its_quote_precision() {
+        let market = crate::models::Market {
+            id: "tonusdt".to_owned(),
+            base_unit: "ton".to_owned(),
+            quote_unit: "usdt".to_owned(),
+            base_precision: 2,
+            quote_precision: 0,
+            display_precision: 0,
+            price_change: 0.0,
+        };
+        let book = OrderBook {
+            coins: Coins::TonUsdt,
+            bids: vec![level(2.6, 1.0), level(1.2, 1.0)],
+            asks: vec![level(2.4, 1.0), level(3.0, 1.0)],
+        };
+        let aggregated = book.aggregate_for_market(&market, None).expect("Invalid tick");
+        assert_eq!(levels_of(&aggregated.bids), vec![(2.0, 1.0, 1), (1.0, 1.0, 1)]);
+        assert_eq!(levels_of(&aggregated.asks), vec![(3.0, 2.0, 2)]);
+    }
+}
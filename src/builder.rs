@@ -0,0 +1,289 @@
+use crate::client::KunaClient;
+use crate::public::KunaPublicClient;
+use crate::transport::Transport;
+use std::sync::Arc;
+use std::time::Duration;
+
+pub const PRODUCTION_URL: &str = "https://api.kuna.io";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Tls {
+    // Plain HTTP, e.g. for a local mock server.
+    Disabled,
+    NativeTls,
+    Rustls,
+}
+
+impl Default for Tls {
+    fn default() -> Tls {
+        if cfg!(feature = "rustls") {
+            Tls::Rustls
+        } else if cfg!(feature = "native-tls") {
+            Tls::NativeTls
+        } else {
+            Tls::Disabled
+        }
+    }
+}
+
+/// Applies the builder wide headers and request timeout on top of any transport.
+struct ConfiguredTransport {
+    inner: Arc<dyn Transport>,
+    request_timeout: Option<Duration>,
+    default_headers: http::HeaderMap,
+}
+
+impl Transport for ConfiguredTransport {
+    fn send(
+        &self,
+        mut request: crate::transport::Request,
+    ) -> crate::transport::BoxFuture<'_, Result<crate::transport::Response, crate::transport::TransportError>> {
+        for (name, value) in self.default_headers.iter() {
+            if !request.headers().contains_key(name) {
+                request.headers_mut().insert(name.clone(), value.clone());
+            }
+        }
+        Box::pin(async move {
+            match self.request_timeout {
+                Some(timeout) => match tokio::time::timeout(timeout, self.inner.send(request)).await {
+                    Ok(response) => response,
+                    Err(_) => Err(format!("Request timed out after {:?}", timeout).into()),
+                },
+                None => self.inner.send(request).await,
+            }
+        })
+    }
+}
+
+pub struct KunaClientBuilder {
+    base_url: url::Url,
+    request_timeout: Option<Duration>,
+    connect_timeout: Option<Duration>,
+    user_agent: Option<String>,
+    default_headers: http::HeaderMap,
+    tls: Tls,
+    credentials: Option<(String, zeroize::Zeroizing<String>)>,
+    transport: Option<Arc<dyn Transport>>,
+    rate_limiter: Option<Arc<crate::limiter::RateLimiter>>,
+    retry_policy: Option<crate::retry::RetryPolicy>,
+    middleware: crate::middleware::MiddlewareChain,
+    quota_tracker: Arc<crate::limiter::QuotaTracker>,
+}
+
+impl Default for KunaClientBuilder {
+    fn default() -> KunaClientBuilder {
+        KunaClientBuilder::production()
+    }
+}
+
+impl KunaClientBuilder {
+    pub fn production() -> KunaClientBuilder {
+        KunaClientBuilder {
+            base_url: url::Url::parse(PRODUCTION_URL).expect("Invalid production url"),
+            request_timeout: Some(Duration::from_secs(30)),
+            connect_timeout: Some(Duration::from_secs(10)),
+            user_agent: Some(format!("kuna_sdk/{}", env!("CARGO_PKG_VERSION"))),
+            default_headers: http::HeaderMap::new(),
+            tls: Tls::default(),
+            credentials: None,
+            transport: None,
+            rate_limiter: None,
+            retry_policy: None,
+            middleware: crate::middleware::MiddlewareChain::default(),
+            quota_tracker: Arc::new(crate::limiter::QuotaTracker::new()),
+        }
+    }
+
+    pub fn base_url(mut self, base_url: url::Url) -> KunaClientBuilder {
+        self.base_url = base_url;
+        self
+    }
+
+    pub fn request_timeout(mut self, timeout: Duration) -> KunaClientBuilder {
+        self.request_timeout = Some(timeout);
+        self
+    }
+
+    pub fn no_request_timeout(mut self) -> KunaClientBuilder {
+        self.request_timeout = None;
+        self
+    }
+
+    /// Only used by the transport built by `KunaClientBuilder` itself.
+    pub fn connect_timeout(mut self, timeout: Duration) -> KunaClientBuilder {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    pub fn user_agent(mut self, user_agent: &str) -> KunaClientBuilder {
+        self.user_agent = Some(user_agent.to_owned());
+        self
+    }
+
+    pub fn default_header(mut self, name: http::header::HeaderName, value: http::HeaderValue) -> KunaClientBuilder {
+        self.default_headers.insert(name, value);
+        self
+    }
+
+    pub fn tls(mut self, tls: Tls) -> KunaClientBuilder {
+        self.tls = tls;
+        self
+    }
+
+    /// The keys are validated when the private client is built.
+    pub fn credentials(mut self, public_key: &str, private_key: &str) -> KunaClientBuilder {
+        self.credentials = Some((public_key.to_owned(), zeroize::Zeroizing::new(private_key.to_owned())));
+        self
+    }
+
+    pub fn with_credentials(self, credentials: &crate::credentials::Credentials) -> KunaClientBuilder {
+        self.credentials(credentials.public_key(), credentials.private_key())
+    }
+
+    /// Replaces the default hyper based transport, timeouts and default headers still apply.
+    pub fn transport(mut self, transport: Arc<dyn Transport>) -> KunaClientBuilder {
+        self.transport = Some(transport);
+        self
+    }
+
+    pub fn rate_limiter(mut self, rate_limiter: Arc<crate::limiter::RateLimiter>) -> KunaClientBuilder {
+        self.rate_limiter = Some(rate_limiter);
+        self
+    }
+
+    pub fn retry_policy(mut self, retry_policy: crate::retry::RetryPolicy) -> KunaClientBuilder {
+        self.retry_policy = Some(retry_policy);
+        self
+    }
+
+    /// Appends `middleware` to the chain shared by the built clients, see `middleware::Middleware`.
+    pub fn middleware(mut self, middleware: Arc<dyn crate::middleware::Middleware>) -> KunaClientBuilder {
+        self.middleware.push(middleware);
+        self
+    }
+
+    /// Replaces the quota tracker shared by every client built by this builder.
+    pub fn quota_tracker(mut self, quota_tracker: Arc<crate::limiter::QuotaTracker>) -> KunaClientBuilder {
+        self.quota_tracker = quota_tracker;
+        self
+    }
+
+    fn default_headers(&self) -> Result<http::HeaderMap, String> {
+        let mut headers = self.default_headers.clone();
+        if let Some(user_agent) = &self.user_agent {
+            let user_agent = match http::HeaderValue::from_str(user_agent) {
+                Ok(user_agent) => user_agent,
+                Err(error) => return Err(format!("Invalid user agent: {:#?}", error)),
+            };
+            headers.entry(http::header::USER_AGENT).or_insert(user_agent);
+        }
+        Ok(headers)
+    }
+
+    #[cfg(feature = "hyper")]
+    fn default_transport(&self) -> Result<Arc<dyn Transport>, String> {
+        let mut connector = hyper::client::HttpConnector::new();
+        connector.set_connect_timeout(self.connect_timeout);
+        match self.tls {
+            // The plain connector refuses https urls, fail here rather than on every request.
+            Tls::Disabled if self.base_url.scheme() == "https" => Err(format!(
+                "{} needs TLS, enable the rustls or native-tls feature or use an http url",
+                self.base_url
+            )),
+            Tls::Disabled => Ok(Arc::new(hyper::Client::builder().build::<_, hyper::Body>(connector))),
+            #[cfg(feature = "native-tls")]
+            Tls::NativeTls => {
+                connector.enforce_http(false);
+                let connector = hyper_tls::HttpsConnector::new_with_connector(connector);
+                Ok(Arc::new(hyper::Client::builder().build::<_, hyper::Body>(connector)))
+            },
+            #[cfg(feature = "rustls")]
+            Tls::Rustls => {
+                connector.enforce_http(false);
+                let connector = hyper_rustls::HttpsConnectorBuilder::new()
+                    .with_native_roots()
+                    .https_or_http()
+                    .enable_http1()
+                    .wrap_connector(connector);
+                Ok(Arc::new(hyper::Client::builder().build::<_, hyper::Body>(connector)))
+            },
+            #[allow(unreachable_patterns)]
+            tls => Err(format!("{:?} support is not enabled, check the crate features", tls)),
+        }
+    }
+
+    #[cfg(not(feature = "hyper"))]
+    fn default_transport(&self) -> Result<Arc<dyn Transport>, String> {
+        Err("No transport configured and the hyper feature is disabled".to_owned())
+    }
+
+    fn build_transport(&self) -> Result<Arc<dyn Transport>, String> {
+        let inner = match &self.transport {
+            Some(transport) => transport.clone(),
+            None => self.default_transport()?,
+        };
+        Ok(Arc::new(ConfiguredTransport {
+            inner,
+            request_timeout: self.request_timeout,
+            default_headers: self.default_headers()?,
+        }))
+    }
+
+    fn configure_public(&self, client: KunaPublicClient) -> KunaPublicClient {
+        let client = match &self.rate_limiter {
+            Some(rate_limiter) => client.with_rate_limiter(rate_limiter.clone()),
+            None => client,
+        };
+        let client = match &self.retry_policy {
+            Some(retry_policy) => client.with_retry_policy(retry_policy.clone()),
+            None => client,
+        };
+        client
+            .with_middleware_chain(self.middleware.clone())
+            .with_quota_tracker(self.quota_tracker.clone())
+    }
+
+    fn configure_private(&self, client: KunaClient) -> KunaClient {
+        let client = match &self.rate_limiter {
+            Some(rate_limiter) => client.with_rate_limiter(rate_limiter.clone()),
+            None => client,
+        };
+        let client = match &self.retry_policy {
+            Some(retry_policy) => client.with_retry_policy(retry_policy.clone()),
+            None => client,
+        };
+        client
+            .with_middleware_chain(self.middleware.clone())
+            .with_quota_tracker(self.quota_tracker.clone())
+    }
+
+    fn private_client(&self, transport: Arc<dyn Transport>) -> Result<KunaClient, String> {
+        let credentials = match &self.credentials {
+            Some((public_key, private_key)) => crate::credentials::Credentials::new(public_key, private_key)?,
+            None => return Err("Credentials are required for the private client".to_owned()),
+        };
+        let auth_context = crate::context::AuthContext::with_credentials(&credentials, self.base_url.clone());
+        Ok(self.configure_private(KunaClient::new(transport, Arc::new(auth_context))))
+    }
+
+    pub fn build_public(&self) -> Result<KunaPublicClient, String> {
+        let transport = self.build_transport()?;
+        Ok(self.configure_public(KunaPublicClient::new(transport, self.base_url.clone())))
+    }
+
+    pub fn build_private(&self) -> Result<KunaClient, String> {
+        let transport = self.build_transport()?;
+        self.private_client(transport)
+    }
+
+    /// Builds both clients over one shared transport, the private one only if credentials were set.
+    pub fn build(&self) -> Result<(KunaPublicClient, Option<KunaClient>), String> {
+        let transport = self.build_transport()?;
+        let public_client = self.configure_public(KunaPublicClient::new(transport.clone(), self.base_url.clone()));
+        let private_client = match self.credentials {
+            Some(_) => Some(self.private_client(transport)?),
+            None => None,
+        };
+        Ok((public_client, private_client))
+    }
+}
@@ -2,27 +2,98 @@ use crate::base;
 use crate::extractor;
 use crate::coin;
 use crate::models;
+use crate::dispatch::DispatchError;
+use crate::dispatch::Dispatcher;
+use crate::response::WithMetadata;
 
-pub struct KunaClient<TConnector> {
-    client: std::sync::Arc<hyper::Client<TConnector>>,
+/// How much earlier than the call an order may appear to be created, as the exchange clock
+/// sees it, and still be taken as the one a failed submission placed.
+pub const RECONCILE_CLOCK_SKEW: std::time::Duration = std::time::Duration::from_secs(10);
+
+pub struct KunaClient {
+    dispatcher: Dispatcher,
     auth_context: std::sync::Arc<crate::context::AuthContext>,
 }
 
-impl<TConnector> KunaClient<TConnector>
-where
-    TConnector: hyper::client::connect::Connect + Send + Sync + Clone + 'static,
-{
+impl KunaClient {
     pub fn new(
-        client: std::sync::Arc<hyper::Client<TConnector>>,
+        transport: std::sync::Arc<dyn crate::transport::Transport>,
         auth_context: std::sync::Arc<crate::context::AuthContext>,
-    ) -> KunaClient<TConnector> {
+    ) -> KunaClient {
         KunaClient {
-            client,
+            dispatcher: Dispatcher::new(transport),
             auth_context,
         }
     }
 
+    pub fn with_rate_limiter(
+        mut self,
+        rate_limiter: std::sync::Arc<crate::limiter::RateLimiter>,
+    ) -> KunaClient {
+        self.dispatcher.rate_limiter = Some(rate_limiter);
+        self
+    }
+
+    pub fn with_retry_policy(
+        mut self,
+        retry_policy: crate::retry::RetryPolicy,
+    ) -> KunaClient {
+        self.dispatcher.retry_policy = retry_policy;
+        self
+    }
+
+    /// Appends `middleware` to the chain run around every request.
+    pub fn with_middleware(
+        mut self,
+        middleware: std::sync::Arc<dyn crate::middleware::Middleware>,
+    ) -> KunaClient {
+        self.dispatcher.middleware.push(middleware);
+        self
+    }
+
+    pub fn with_middleware_chain(
+        mut self,
+        middleware: crate::middleware::MiddlewareChain,
+    ) -> KunaClient {
+        self.dispatcher.middleware = middleware;
+        self
+    }
+
+    /// Shares the exchange reported quota, and the pauses it causes, with other clients of the key.
+    pub fn with_quota_tracker(
+        mut self,
+        quota: std::sync::Arc<crate::limiter::QuotaTracker>,
+    ) -> KunaClient {
+        self.dispatcher.quota = quota;
+        self
+    }
+
+    pub fn rate_limiter(&self) -> Option<&std::sync::Arc<crate::limiter::RateLimiter>> {
+        self.dispatcher.rate_limiter.as_ref()
+    }
+
+    pub fn retry_policy(&self) -> &crate::retry::RetryPolicy {
+        &self.dispatcher.retry_policy
+    }
+
+    pub fn middleware(&self) -> &crate::middleware::MiddlewareChain {
+        &self.dispatcher.middleware
+    }
+
+    pub fn quota_tracker(&self) -> &std::sync::Arc<crate::limiter::QuotaTracker> {
+        &self.dispatcher.quota
+    }
+
+    /// The last quota the exchange reported for private endpoints.
+    pub fn rate_limit_status(&self) -> Option<crate::response::RateLimitStatus> {
+        self.dispatcher.quota.status(base::Endpoint::Balance)
+    }
+
     pub async fn get_balance(&self) -> Result<Vec<crate::models::Currency>, String> {
+        self.get_balance_with_metadata().await.map(WithMetadata::into_inner)
+    }
+
+    pub async fn get_balance_with_metadata(&self) -> Result<WithMetadata<Vec<crate::models::Currency>>, String> {
         let mut url = self.auth_context.base_url.clone();
         url.path_segments_mut()
             .expect("Invalid url")
@@ -30,44 +101,93 @@ where
             .push(base::AUTH)
             .push(base::REQUEST)
             .push(base::WALLETS);
-        let request = match base::sign_request(
-            base::default_request_builder(&url),
-            &url,
-            None,
-            &self.auth_context,
-        )
-        .method(hyper::Method::POST)
-        .header("Content-Type", "application/json")
-        .body(hyper::Body::from("{}"))
-        {
-            Ok(request) => request,
-            Err(error) => return Err(format!("Failed to create request: {:#?}", error)),
-        };
-        let (_header, body) = match self
-            .client
-            .request(request)
-            .await {
-            Ok(response) => response.into_parts(),
-            Err(error) => return Err(format!("Failed to create response: {:#?}", error)),
-        };
-        let currency = match extractor::read_body::<crate::models::Currencies>(body)
-            .await {
+        let response = self.dispatcher.send_signed(base::Endpoint::Balance, None, &self.auth_context, || {
+            (
+                base::default_request_builder(&url)
+                    .method(http::Method::POST)
+                    .header("Content-Type", "application/json"),
+                b"{}".to_vec(),
+            )
+        })
+        .await?;
+        let currency = match extractor::parse_body::<crate::models::Currencies>(&response.body) {
             Some(currency) => currency,
             None => return Err("Failed to read body: {:#?}".to_owned()),
         };
         let result: Vec<_> = currency
-            .iter()
-            .filter_map(|currency_enties| {
-                crate::models::Currency::from(currency_enties)
-            })
+            .into_iter()
+            .map(crate::models::Currency::from)
             .collect();
-        Ok(result)
+        Ok(WithMetadata {
+            value: result,
+            metadata: response.into_metadata(),
+        })
     }
 
+    /// Order submission is never retried blindly. When an attempt fails in a way that leaves its
+    /// outcome unknown, the open orders and then the order history are checked, two extra signed
+    /// requests that successful submissions never pay. An order with the same symbol, type,
+    /// amount and price created since the call started, less `RECONCILE_CLOCK_SKEW`, is taken as
+    /// the placed one, otherwise the order is resent. Nothing is resent when checking fails.
+    ///
+    /// Orders are matched by their fields, so an identical order placed on the same key within
+    /// that window, by this or another task, can be taken for this one. Turn reconciliation off
+    /// with `RetryPolicy::with_order_reconciliation(false)` to get the error instead.
     pub async fn create_order(
         &self,
         order: crate::models::CreateOrder,
-    ) -> Result<models::CreateOrderResponse, String> {
+    ) -> Result<crate::order::Order, String> {
+        self.create_order_with_metadata(order).await.map(WithMetadata::into_inner)
+    }
+
+    /// If the order was only found by reconciliation, the metadata is the one of the query that found it.
+    pub async fn create_order_with_metadata(
+        &self,
+        order: crate::models::CreateOrder,
+    ) -> Result<WithMetadata<crate::order::Order>, String> {
+        let retry_policy = &self.dispatcher.retry_policy;
+        let created_after = chrono::Utc::now().timestamp_millis() - RECONCILE_CLOCK_SKEW.as_millis() as i64;
+        let mut attempt = 1;
+        loop {
+            let error = match self.submit_order(&order).await {
+                Ok(response) => return Ok(response),
+                Err(error) if error.is_transient() => String::from(error),
+                Err(error) => return Err(error.into()),
+            };
+            if !retry_policy.reconciles_orders() {
+                return Err(error);
+            }
+            match self.find_submitted_order(&order, created_after).await {
+                Ok(Some(placed)) => {
+                    log::warn!("Order submission failed but the order was placed: {}", error);
+                    return Ok(placed);
+                },
+                Ok(None) if attempt < retry_policy.max_attempts => {
+                    let delay = retry_policy.delay(attempt);
+                    log::warn!(
+                        "Order was not placed on attempt {}, resending in {:?}: {}",
+                        attempt,
+                        delay,
+                        error
+                    );
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                },
+                Ok(None) => return Err(error),
+                Err(reconcile_error) => {
+                    return Err(format!(
+                        "{}\nThe order may have been placed, checking failed: {}",
+                        error, reconcile_error
+                    ))
+                },
+            }
+        }
+    }
+
+    async fn submit_order(
+        &self,
+        order: &crate::models::CreateOrder,
+    ) -> Result<WithMetadata<crate::order::Order>, DispatchError> {
         let mut url = self.auth_context.base_url.clone();
         url.path_segments_mut()
             .expect("Invalid url")
@@ -76,47 +196,77 @@ where
             .push(base::W)
             .push(base::ORDER)
             .push(base::SUBMIT);
-        let body = match serde_json::to_string(&order) {
+        let body = match serde_json::to_string(order) {
             Ok(body) => body,
-            Err(error) => return Err(format!("Serialization error: {:#?}", error)),
-        };
-        let request = match base::sign_request(
-            base::default_request_builder(&url),
-            &url,
-            Some(&body),
-            &self.auth_context,
-        )
-        .header("Content-Type", "application/json")
-        .method(hyper::Method::POST)
-        .body(hyper::Body::from(body)) {
-            Ok(request) => request,
-            Err(error) => return Err(format!("Failed to create request: {:#?}", error)),
+            Err(error) => return Err(DispatchError::Rejected(format!("Serialization error: {:#?}", error))),
         };
-        let (_header, body) = match self
-            .client
-            .request(request)
-            .await {
-            Ok(response) => response.into_parts(),
-            Err(error) => return Err(format!("Failed to create response: {:#?}", error)),
-        };
-        let body = match extractor::read_body::<models::CreateOrderResponseRaw>(body).await {
+        let response = self.dispatcher.send_signed(base::Endpoint::CreateOrder, Some(&order.symbol), &self.auth_context, || {
+            (
+                base::default_request_builder(&url)
+                    .header("Content-Type", "application/json")
+                    .method(http::Method::POST),
+                body.clone().into_bytes(),
+            )
+        })
+        .await?;
+        let body = match extractor::parse_body::<models::CreateOrderResponseRaw>(&response.body) {
             Some(body) => body,
-            None => return Err("Failed to read body".to_owned()),
+            None => return Err(DispatchError::Rejected("Failed to read body".to_owned())),
         };
         use std::convert::TryFrom;
-        match models::CreateOrderResponse::try_from(body.clone()) {
-            Ok(response) => Ok(response),
+        match crate::order::Order::try_from(body.clone()) {
+            Ok(placed) => Ok(WithMetadata {
+                value: placed,
+                metadata: response.into_metadata(),
+            }),
             Err(error) => {
                 log::error!("Invalid json: {:#?}", body);
-                Err(format!("Failed to convert body to value: {:#?}", error))
+                Err(DispatchError::Rejected(format!("Failed to convert body to value: {:#?}", error)))
             },
         }
     }
 
+    // Looks for the newest matching order created after `created_after`, in milliseconds, among
+    // the open orders first, then in the history in case it was filled right away. Orders without
+    // a creation time are candidates too, resending one that was placed would be worse.
+    async fn find_submitted_order(
+        &self,
+        order: &crate::models::CreateOrder,
+        created_after: i64,
+    ) -> Result<Option<WithMetadata<crate::order::Order>>, String> {
+        use std::convert::TryFrom;
+        let coins = coin::Coins::try_from(order.symbol.as_ref())?;
+        for endpoint in [base::Endpoint::MyOrders, base::Endpoint::OrderHistory] {
+            let orders = self.get_orders(endpoint, coins.clone()).await?;
+            let placed = orders
+                .value
+                .iter()
+                .filter(|placed| {
+                    placed
+                        .created_at
+                        .is_none_or(|created_at| created_at.timestamp_millis() >= created_after)
+                })
+                .filter(|placed| order.matches(placed))
+                .max_by_key(|placed| placed.id)
+                .cloned();
+            if let Some(placed) = placed {
+                return Ok(Some(orders.map(|_| placed)));
+            }
+        }
+        Ok(None)
+    }
+
     pub async fn delete_order(
         &self,
         cancel_order: crate::models::CancelOrderRequest,
-    ) -> Result<crate::order::CanceledOrder, String> {
+    ) -> Result<crate::order::Order, String> {
+        self.delete_order_with_metadata(cancel_order).await.map(WithMetadata::into_inner)
+    }
+
+    pub async fn delete_order_with_metadata(
+        &self,
+        cancel_order: crate::models::CancelOrderRequest,
+    ) -> Result<WithMetadata<crate::order::Order>, String> {
         let mut url = self.auth_context.base_url.clone();
         url.path_segments_mut()
             .expect("Invalid url")
@@ -127,84 +277,107 @@ where
             Ok(body) => body,
             Err(error) => return Err(format!("Serialization error: {:#?}", error)),
         };
-        let request = match base::sign_request(
-            base::default_request_builder(&url),
-            &url,
-            Some(&body),
-            &self.auth_context,
-        )
-        .method(hyper::Method::POST)
-        .body(hyper::Body::from(body)) {
-            Ok(request) => request,
-            Err(error) => return Err(format!("Failed to create request: {:#?}", error)),
-        };
-        let (header, body) = match self
-            .client
-            .request(request)
-            .await {
-            Ok(response) => response.into_parts(),
-            Err(error) => return Err(format!("Failed to create response: {:#?}", error)),
-        };
-        let body_result = match extractor::read_body::<crate::models::CanceledOrderResponse>(body)
-            .await {
+        let response = self.dispatcher.send_signed(base::Endpoint::CancelOrder, None, &self.auth_context, || {
+            (
+                base::default_request_builder(&url)
+                    .method(http::Method::POST),
+                body.clone().into_bytes(),
+            )
+        })
+        .await?;
+        let body_result = match extractor::parse_body::<crate::models::CanceledOrderResponse>(&response.body) {
             Some(body) => body,
             None => return Err("Failed to read body: {:#?}".to_owned()),
         };
         use std::convert::TryFrom;
-        match crate::order::CanceledOrder::try_from(body_result) {
-            Ok(cancel_order) => Ok(cancel_order),
-            Err(error) => Err(format!("Header: {:#?}\nError: {}", header, error)),
+        match crate::order::Order::try_from(body_result) {
+            Ok(cancel_order) => Ok(WithMetadata {
+                value: cancel_order,
+                metadata: response.into_metadata(),
+            }),
+            Err(error) => Err(format!(
+                "Status: {}\nHeaders: {:#?}\nError: {}",
+                response.parts.status,
+                base::RedactedHeaders(&response.parts.headers),
+                error
+            )),
         }
     }
-    
+
     pub async fn get_my_orders(
         &self,
         coins: coin::Coins,
-    ) -> Result<Vec<models::MyOrderResponse>, String> {
+    ) -> Result<Vec<crate::order::Order>, String> {
+        self.get_my_orders_with_metadata(coins).await.map(WithMetadata::into_inner)
+    }
+
+    pub async fn get_my_orders_with_metadata(
+        &self,
+        coins: coin::Coins,
+    ) -> Result<WithMetadata<Vec<crate::order::Order>>, String> {
+        self.get_orders(base::Endpoint::MyOrders, coins).await
+    }
+
+    pub async fn get_order_history(
+        &self,
+        coins: coin::Coins,
+    ) -> Result<Vec<crate::order::Order>, String> {
+        self.get_order_history_with_metadata(coins).await.map(WithMetadata::into_inner)
+    }
+
+    pub async fn get_order_history_with_metadata(
+        &self,
+        coins: coin::Coins,
+    ) -> Result<WithMetadata<Vec<crate::order::Order>>, String> {
+        self.get_orders(base::Endpoint::OrderHistory, coins).await
+    }
+
+    async fn get_orders(
+        &self,
+        endpoint: base::Endpoint,
+        coins: coin::Coins,
+    ) -> Result<WithMetadata<Vec<crate::order::Order>>, String> {
         let mut url = self.auth_context.base_url.clone();
-        url.path_segments_mut()
-            .expect("Invalid url")
-            .push(base::VERSION)
-            .push(base::AUTH)
-            .push(base::REQUEST)
-            .push(base::ORDERS)
-            .push(&coins.to_string());
-        let request = match base::sign_request(
-            base::default_request_builder(&url),
-            &url,
-            None,
-            &self.auth_context,
-        )
-        .method(hyper::Method::POST)
-        .header("Content-Type", "application/json")
-        .body(hyper::Body::from("{}"))
         {
-            Ok(request) => request,
-            Err(error) => return Err(format!("Failed to create request: {:#?}", error)),
-        };
-        let (_header, body) = match self
-            .client
-            .request(request)
-            .await {
-            Ok(response) => response.into_parts(),
-            Err(error) => return Err(format!("Failed to create response: {:#?}", error)),
-        };
-        let orders = match extractor::read_body::<Vec<models::CreateOrderResponseRaw>>(body)
-            .await {
-            Some(currency) => currency,
+            let mut segments = url.path_segments_mut().expect("Invalid url");
+            segments
+                .push(base::VERSION)
+                .push(base::AUTH)
+                .push(base::REQUEST)
+                .push(base::ORDERS)
+                .push(&coins.to_string());
+            if endpoint == base::Endpoint::OrderHistory {
+                segments.push(base::HISTORY);
+            }
+        }
+        let response = self.dispatcher.send_signed(endpoint, Some(&coins.to_string()), &self.auth_context, || {
+            (
+                base::default_request_builder(&url)
+                    .method(http::Method::POST)
+                    .header("Content-Type", "application/json"),
+                b"{}".to_vec(),
+            )
+        })
+        .await?;
+        let orders = match extractor::parse_body::<Vec<models::CreateOrderResponseRaw>>(&response.body) {
+            Some(orders) => orders,
             None => return Err("Failed to read body: {:#?}".to_owned()),
         };
-        log::debug!("My Orders: {:#?}", orders);
+        log::debug!("Orders: {:#?}", orders);
         use std::convert::TryFrom;
         let result: Vec<_> = orders
             .into_iter()
-            .filter_map(|order| {
-                match models::MyOrderResponse::try_from(order) {
-                    Ok(order) => Some(order),
-                    Err(_) => None,
-                }
+            .filter_map(|order| match crate::order::Order::try_from(order) {
+                Ok(order) => Some(order),
+                Err(error) => {
+                    log::warn!("Skipping unparsable order: {}", error);
+                    None
+                },
             })
             .collect();
-        Ok(result)
+        Ok(WithMetadata {
+            value: result,
+            metadata: response.into_metadata(),
+        })
     }
 }
@@ -1,3 +1,6 @@
+/// `Unknown` is meant for names that are not a known coin, as `Coin::from` produces it.
+/// It is serialized as its name and names are matched case insensitively when read back,
+/// so an `Unknown` holding a known name, e.g. `Unknown("TON")`, deserializes as that coin.
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum Coin {
     TON,
@@ -28,19 +31,33 @@ impl std::fmt::Display for Coin {
     }
 }
 
-#[derive(Clone, Debug)]
+/// Serialized as its lowercase name, `Unknown` coins as the name they were created from.
+impl serde::Serialize for Coin {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Coin {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Coin, D::Error> {
+        let coin = String::deserialize(deserializer)?;
+        Ok(Coin::from(coin.as_ref()))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum Coins {
     TonUsdt,
 }
 
 impl Coins {
-    fn base_coin(&self) -> Coin {
+    pub fn base_coin(&self) -> Coin {
         match self {
             Coins::TonUsdt => Coin::TON,
         }
     }
 
-    fn quote_coin(&self) -> Coin {
+    pub fn quote_coin(&self) -> Coin {
         match self {
             Coins::TonUsdt => Coin::USDT,
         }
@@ -63,3 +80,18 @@ impl std::convert::TryFrom<&str> for Coins {
         }
     }
 }
+
+/// Serialized as the market symbol, e.g. "tonusdt".
+impl serde::Serialize for Coins {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Coins {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Coins, D::Error> {
+        use std::convert::TryFrom;
+        let symbol = String::deserialize(deserializer)?;
+        Coins::try_from(symbol.as_ref()).map_err(serde::de::Error::custom)
+    }
+}
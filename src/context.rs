@@ -1,8 +1,8 @@
-use hmac::NewMac;
-use hmac::Mac;
+use crate::signer::Signer;
+use std::sync::Arc;
 
 pub struct AuthContext {
-    private_key: String,
+    signer: Arc<dyn Signer>,
     pub public_key: String,
     pub base_url: url::Url,
 }
@@ -13,19 +13,60 @@ impl AuthContext {
         public_key: String,
         base_url: url::Url
     ) -> AuthContext {
+        AuthContext::with_signer(
+            Arc::new(crate::signer::HmacSigner::new(private_key)),
+            public_key,
+            base_url,
+        )
+    }
+
+    /// Signs requests with `signer` instead of holding the private key.
+    pub fn with_signer(signer: Arc<dyn Signer>, public_key: String, base_url: url::Url) -> AuthContext {
         AuthContext {
-            private_key,
+            signer,
             public_key,
             base_url,
         }
     }
 
-    pub fn sign(&self, message: &str) -> String {
-        let mut signature = hmac::Hmac::<sha2::Sha384>::new_from_slice(
-            self.private_key.as_bytes())
-            .expect("HMAC can take key of any size");
-        signature.update(message.as_bytes());
-        let signature = signature.finalize();
-        hex::encode(signature.into_bytes())
+    pub fn with_credentials(credentials: &crate::credentials::Credentials, base_url: url::Url) -> AuthContext {
+        AuthContext::new(
+            credentials.private_key().to_owned(),
+            credentials.public_key().to_owned(),
+            base_url,
+        )
+    }
+
+    pub fn from_env(base_url: url::Url) -> Result<AuthContext, crate::credentials::CredentialsError> {
+        let credentials = crate::credentials::Credentials::from_env()?;
+        Ok(AuthContext::with_credentials(&credentials, base_url))
+    }
+
+    pub fn from_file(
+        path: &std::path::Path,
+        base_url: url::Url,
+    ) -> Result<AuthContext, crate::credentials::CredentialsError> {
+        let credentials = crate::credentials::Credentials::from_file(path)?;
+        Ok(AuthContext::with_credentials(&credentials, base_url))
+    }
+
+    pub fn signer(&self) -> &Arc<dyn Signer> {
+        &self.signer
+    }
+
+    pub async fn sign(&self, message: &str) -> Result<String, String> {
+        self.signer.sign(message).await
+    }
+}
+
+/// Both keys are redacted, only the base url is shown.
+impl std::fmt::Debug for AuthContext {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        formatter
+            .debug_struct("AuthContext")
+            .field("private_key", &crate::base::REDACTED)
+            .field("public_key", &crate::base::REDACTED)
+            .field("base_url", &self.base_url.as_str())
+            .finish()
     }
 }
@@ -0,0 +1,249 @@
+use std::path::Path;
+use std::path::PathBuf;
+
+pub const PUBLIC_KEY_VAR: &str = "KUNA_PUBLIC_KEY";
+pub const PRIVATE_KEY_VAR: &str = "KUNA_PRIVATE_KEY";
+
+#[derive(Clone, Debug)]
+pub enum CredentialsError {
+    // Named after the variable, file field or secret that was looked up.
+    Missing(String),
+    Malformed {
+        name: String,
+        reason: &'static str,
+    },
+    // The file can be read by other users, `mode` holds its unix permission bits.
+    InsecurePermissions {
+        path: PathBuf,
+        mode: u32,
+    },
+    Io {
+        path: PathBuf,
+        error: String,
+    },
+    InvalidFile {
+        path: PathBuf,
+        error: String,
+    },
+    Provider(String),
+}
+
+impl std::fmt::Display for CredentialsError {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CredentialsError::Missing(name) => write!(formatter, "Missing credential {}", name),
+            CredentialsError::Malformed { name, reason } => {
+                write!(formatter, "Malformed credential {}: {}", name, reason)
+            },
+            CredentialsError::InsecurePermissions { path, mode } => write!(
+                formatter,
+                "Credentials file {:?} is accessible by other users (mode {:o}), restrict it with chmod 600",
+                path, mode
+            ),
+            CredentialsError::Io { path, error } => {
+                write!(formatter, "Failed to read credentials file {:?}: {}", path, error)
+            },
+            CredentialsError::InvalidFile { path, error } => {
+                write!(formatter, "Invalid credentials file {:?}: {}", path, error)
+            },
+            CredentialsError::Provider(error) => write!(formatter, "Secret provider failed: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for CredentialsError {}
+
+impl From<CredentialsError> for String {
+    fn from(error: CredentialsError) -> String {
+        error.to_string()
+    }
+}
+
+/// Source of named secrets, e.g. a system keyring or a vault client.
+pub trait SecretProvider: Send + Sync {
+    /// Returns `Ok(None)` if the provider has no secret called `name`.
+    fn secret(&self, name: &str) -> Result<Option<String>, String>;
+}
+
+/// Reads secrets from environment variables of the same name.
+pub struct EnvSecretProvider;
+
+impl SecretProvider for EnvSecretProvider {
+    fn secret(&self, name: &str) -> Result<Option<String>, String> {
+        match std::env::var(name) {
+            Ok(value) => Ok(Some(value)),
+            Err(std::env::VarError::NotPresent) => Ok(None),
+            Err(error) => Err(format!("{}: {}", name, error)),
+        }
+    }
+}
+
+impl SecretProvider for std::collections::HashMap<String, String> {
+    fn secret(&self, name: &str) -> Result<Option<String>, String> {
+        Ok(self.get(name).cloned())
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct CredentialsFile {
+    public_key: Option<String>,
+    private_key: Option<String>,
+}
+
+/// A validated Kuna key pair, the private key is wiped from memory on drop.
+#[derive(Clone)]
+pub struct Credentials {
+    public_key: String,
+    private_key: zeroize::Zeroizing<String>,
+}
+
+impl Credentials {
+    pub fn new(public_key: &str, private_key: &str) -> Result<Credentials, CredentialsError> {
+        validate("public key", public_key)?;
+        validate("private key", private_key)?;
+        Ok(Credentials {
+            public_key: public_key.to_owned(),
+            private_key: zeroize::Zeroizing::new(private_key.to_owned()),
+        })
+    }
+
+    /// Reads `KUNA_PUBLIC_KEY` and `KUNA_PRIVATE_KEY`.
+    pub fn from_env() -> Result<Credentials, CredentialsError> {
+        Credentials::from_provider(&EnvSecretProvider, PUBLIC_KEY_VAR, PRIVATE_KEY_VAR)
+    }
+
+    /// Reads a json file with `public_key` and `private_key`, other fields are ignored.
+    /// On unix the file must not be accessible by group or others.
+    pub fn from_file(path: &Path) -> Result<Credentials, CredentialsError> {
+        Credentials::from_file_contents(path, &read_protected_file(path)?)
+    }
+
+    /// Parses the contents of a credentials file read with `read_protected_file`,
+    /// for callers that also need its other fields. `path` only labels errors.
+    pub fn from_file_contents(path: &Path, json: &[u8]) -> Result<Credentials, CredentialsError> {
+        let file = match serde_json::from_slice::<CredentialsFile>(json) {
+            Ok(file) => file,
+            Err(error) => {
+                return Err(CredentialsError::InvalidFile {
+                    path: path.to_owned(),
+                    error: error.to_string(),
+                })
+            },
+        };
+        match (file.public_key, file.private_key.map(zeroize::Zeroizing::new)) {
+            (Some(public_key), Some(private_key)) => Credentials::new(&public_key, &private_key),
+            (None, _) => Err(CredentialsError::Missing(format!("public_key in {:?}", path))),
+            (_, None) => Err(CredentialsError::Missing(format!("private_key in {:?}", path))),
+        }
+    }
+
+    pub fn from_provider(
+        provider: &dyn SecretProvider,
+        public_key_name: &str,
+        private_key_name: &str,
+    ) -> Result<Credentials, CredentialsError> {
+        let secret = |name: &str| match provider.secret(name) {
+            Ok(Some(secret)) if !secret.is_empty() => Ok(secret),
+            Ok(_) => Err(CredentialsError::Missing(name.to_owned())),
+            Err(error) => Err(CredentialsError::Provider(error)),
+        };
+        let public_key = secret(public_key_name)?;
+        let private_key = zeroize::Zeroizing::new(secret(private_key_name)?);
+        Credentials::new(&public_key, &private_key)
+    }
+
+    pub fn public_key(&self) -> &str {
+        &self.public_key
+    }
+
+    pub fn private_key(&self) -> &str {
+        &self.private_key
+    }
+}
+
+impl std::fmt::Debug for Credentials {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        formatter
+            .debug_struct("Credentials")
+            .field("public_key", &crate::base::REDACTED)
+            .field("private_key", &crate::base::REDACTED)
+            .finish()
+    }
+}
+
+/// Reads a file holding secrets into a buffer wiped on drop. On unix the file must not
+/// be accessible by group or others, checked on the opened file before reading it.
+pub fn read_protected_file(path: &Path) -> Result<zeroize::Zeroizing<Vec<u8>>, CredentialsError> {
+    use std::io::Read;
+    let io_error = |error: std::io::Error| CredentialsError::Io {
+        path: path.to_owned(),
+        error: error.to_string(),
+    };
+    // Checked and read through the same handle, the path may be swapped in between.
+    let mut file = std::fs::File::open(path).map_err(io_error)?;
+    let metadata = file.metadata().map_err(io_error)?;
+    check_permissions(&metadata, path)?;
+    // Sized up front so that the buffer is not reallocated, leaving copies behind.
+    let mut contents = zeroize::Zeroizing::new(Vec::with_capacity(metadata.len() as usize + 1));
+    file.read_to_end(&mut contents).map_err(io_error)?;
+    Ok(contents)
+}
+
+fn validate(name: &str, key: &str) -> Result<(), CredentialsError> {
+    let malformed = |reason| {
+        Err(CredentialsError::Malformed {
+            name: name.to_owned(),
+            reason,
+        })
+    };
+    if key.is_empty() {
+        malformed("empty")
+    } else if key.trim() != key {
+        malformed("leading or trailing whitespace")
+    } else if !key.chars().all(|character| character.is_ascii_graphic()) {
+        malformed("only printable ascii characters are allowed")
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(unix)]
+fn check_permissions(metadata: &std::fs::Metadata, path: &Path) -> Result<(), CredentialsError> {
+    use std::os::unix::fs::PermissionsExt;
+    let mode = metadata.permissions().mode() & 0o777;
+    if mode & 0o077 != 0 {
+        return Err(CredentialsError::InsecurePermissions {
+            path: path.to_owned(),
+            mode,
+        });
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn check_permissions(_metadata: &std::fs::Metadata, _path: &Path) -> Result<(), CredentialsError> {
+    Ok(())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_permissions_are_checked_on_the_opened_file() {
+        use std::os::unix::fs::PermissionsExt;
+        let path = std::env::temp_dir().join(format!("kuna-credentials-{}.json", std::process::id()));
+        std::fs::write(&path, r#"{"public_key": "public", "private_key": "private"}"#).expect("Failed to write file");
+
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).expect("Failed to set permissions");
+        let insecure = Credentials::from_file(&path);
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600)).expect("Failed to set permissions");
+        let credentials = Credentials::from_file(&path);
+        std::fs::remove_file(&path).expect("Failed to remove file");
+
+        assert!(matches!(insecure, Err(CredentialsError::InsecurePermissions { mode: 0o644, .. })));
+        let credentials = credentials.expect("Failed to read credentials");
+        assert_eq!(credentials.public_key(), "public");
+        assert_eq!(credentials.private_key(), "private");
+    }
+}
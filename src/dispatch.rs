@@ -0,0 +1,203 @@
+use crate::base::Endpoint;
+use crate::context::AuthContext;
+use crate::limiter::QuotaTracker;
+use crate::limiter::RateLimiter;
+use crate::middleware::Middleware;
+use crate::middleware::MiddlewareChain;
+use crate::retry::RetryPolicy;
+use crate::transport::Transport;
+use std::sync::Arc;
+
+pub(crate) struct RawResponse {
+    pub parts: http::response::Parts,
+    pub body: Vec<u8>,
+    pub round_trip: std::time::Duration,
+    pub attempts: u32,
+}
+
+impl RawResponse {
+    pub fn into_metadata(self) -> crate::response::ResponseMetadata {
+        crate::response::ResponseMetadata::new(
+            self.parts.status,
+            self.parts.headers,
+            self.body,
+            self.round_trip,
+            self.attempts,
+        )
+    }
+}
+
+pub(crate) enum DispatchError {
+    // The request was never accepted by the exchange.
+    Rejected(String),
+    // The request may or may not have reached the exchange.
+    Transient(String),
+    // The exchange answered 429 without processing the request.
+    RateLimited(String),
+}
+
+impl DispatchError {
+    pub fn is_transient(&self) -> bool {
+        matches!(self, DispatchError::Transient(_))
+    }
+}
+
+impl From<DispatchError> for String {
+    fn from(error: DispatchError) -> String {
+        match error {
+            DispatchError::Rejected(error) => error,
+            DispatchError::Transient(error) => error,
+            DispatchError::RateLimited(error) => error,
+        }
+    }
+}
+
+pub(crate) struct Dispatcher {
+    transport: Arc<dyn Transport>,
+    pub rate_limiter: Option<Arc<RateLimiter>>,
+    pub retry_policy: RetryPolicy,
+    pub middleware: MiddlewareChain,
+    pub quota: Arc<QuotaTracker>,
+}
+
+impl Dispatcher {
+    pub fn new(transport: Arc<dyn Transport>) -> Dispatcher {
+        Dispatcher {
+            transport,
+            rate_limiter: None,
+            retry_policy: RetryPolicy::default(),
+            middleware: MiddlewareChain::default(),
+            quota: Arc::new(QuotaTracker::new()),
+        }
+    }
+
+    /// Sends the request whose builder and body are produced by `make_request`, rebuilding
+    /// it on every attempt so that each one goes through the middleware and carries a fresh
+    /// nonce and signature. `market` only labels telemetry.
+    pub async fn send<F>(
+        &self,
+        endpoint: Endpoint,
+        market: Option<&str>,
+        make_request: F,
+    ) -> Result<RawResponse, DispatchError>
+    where
+        F: Fn() -> (http::request::Builder, Vec<u8>),
+    {
+        crate::telemetry::call(endpoint, market, self.send_with(endpoint, None, make_request)).await
+    }
+
+    /// Like `send`, signing every attempt with `auth`.
+    pub async fn send_signed<F>(
+        &self,
+        endpoint: Endpoint,
+        market: Option<&str>,
+        auth: &AuthContext,
+        make_request: F,
+    ) -> Result<RawResponse, DispatchError>
+    where
+        F: Fn() -> (http::request::Builder, Vec<u8>),
+    {
+        crate::telemetry::call(endpoint, market, self.send_with(endpoint, Some(auth), make_request)).await
+    }
+
+    async fn send_with<F>(
+        &self,
+        endpoint: Endpoint,
+        auth: Option<&AuthContext>,
+        make_request: F,
+    ) -> Result<RawResponse, DispatchError>
+    where
+        F: Fn() -> (http::request::Builder, Vec<u8>),
+    {
+        let mut attempt = 1;
+        loop {
+            let error = match self.send_once(endpoint, attempt, auth, &make_request).await {
+                Ok(response) => return Ok(response),
+                Err(DispatchError::Transient(error)) => error,
+                // Not processed, so it is safe to resend once the quota tracker lets it through.
+                Err(DispatchError::RateLimited(error)) if attempt < self.retry_policy.max_attempts => {
+                    log::warn!("{} attempt {} was rate limited: {}", endpoint, attempt, error);
+                    attempt += 1;
+                    continue;
+                },
+                Err(error) => return Err(error),
+            };
+            if !self.retry_policy.should_retry(endpoint, attempt) {
+                return Err(DispatchError::Transient(error));
+            }
+            let delay = self.retry_policy.delay(attempt);
+            log::warn!(
+                "{} attempt {} failed, retrying in {:?}: {}",
+                endpoint,
+                attempt,
+                delay,
+                error
+            );
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    async fn send_once<F>(
+        &self,
+        endpoint: Endpoint,
+        attempt: u32,
+        auth: Option<&AuthContext>,
+        make_request: &F,
+    ) -> Result<RawResponse, DispatchError>
+    where
+        F: Fn() -> (http::request::Builder, Vec<u8>),
+    {
+        self.quota.wait(endpoint).await;
+        if let Some(rate_limiter) = &self.rate_limiter {
+            rate_limiter.acquire(endpoint).await;
+        }
+        let (builder, body) = make_request();
+        let request = self
+            .middleware
+            .before_send(endpoint, builder, &body)
+            .body(body)
+            .map_err(|error| DispatchError::Rejected(format!("Failed to create request: {:#?}", error)))?;
+        let request = match auth {
+            Some(auth) => crate::base::sign_request(request, auth)
+                .await
+                .map_err(DispatchError::Rejected)?,
+            None => request,
+        };
+        let started = std::time::Instant::now();
+        let (parts, body) = crate::telemetry::attempt(endpoint, attempt, self.transport.send(request))
+            .await
+            .map_err(|error| match error {
+                crate::transport::TransportError::Transient(error) => DispatchError::Transient(error),
+                crate::transport::TransportError::Rejected(error) => DispatchError::Rejected(error),
+            })?
+            .into_parts();
+        let round_trip = started.elapsed();
+        self.middleware.after_receive(endpoint, parts.status, &parts.headers, &body);
+        let rate_limited = parts.status == http::StatusCode::TOO_MANY_REQUESTS;
+        self.quota.observe(endpoint, crate::response::RateLimitStatus::from_headers(&parts.headers), rate_limited);
+        if rate_limited {
+            return Err(DispatchError::RateLimited(format!(
+                "Rate limited{}: {}",
+                match self.quota.paused_for(endpoint) {
+                    Some(pause) => format!(", retry after {:?}", pause),
+                    None => String::new(),
+                },
+                String::from_utf8_lossy(&body)
+            )));
+        }
+        if parts.status.is_server_error() {
+            return Err(DispatchError::Transient(format!(
+                "Server error {}: {}",
+                parts.status,
+                String::from_utf8_lossy(&body)
+            )));
+        }
+        Ok(RawResponse {
+            parts,
+            body,
+            round_trip,
+            attempts: attempt,
+        })
+    }
+}
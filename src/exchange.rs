@@ -0,0 +1,350 @@
+use crate::coin::Coins;
+use crate::models;
+use crate::order::Order;
+use std::sync::Arc;
+
+// Kept here too for implementors of the traits below.
+pub use crate::transport::BoxFuture;
+
+pub trait MarketData: Send + Sync {
+    fn get_orderbook(&self, coins: Coins) -> BoxFuture<'_, Result<models::OrderBook, String>>;
+
+    fn get_markets(&self) -> BoxFuture<'_, Result<models::Markets, String>>;
+}
+
+pub trait Trading: Send + Sync {
+    fn get_balance(&self) -> BoxFuture<'_, Result<Vec<models::Currency>, String>>;
+
+    fn create_order(
+        &self,
+        order: models::CreateOrder,
+    ) -> BoxFuture<'_, Result<Order, String>>;
+
+    fn delete_order(
+        &self,
+        cancel_order: models::CancelOrderRequest,
+    ) -> BoxFuture<'_, Result<Order, String>>;
+
+    fn get_my_orders(&self, coins: Coins) -> BoxFuture<'_, Result<Vec<Order>, String>>;
+
+    fn get_order_history(&self, coins: Coins) -> BoxFuture<'_, Result<Vec<Order>, String>>;
+}
+
+/// Everything a strategy needs from an exchange, implemented for any type providing both halves.
+pub trait Exchange: MarketData + Trading {}
+
+impl<T: MarketData + Trading + ?Sized> Exchange for T {}
+
+impl MarketData for crate::public::KunaPublicClient {
+    fn get_orderbook(&self, coins: Coins) -> BoxFuture<'_, Result<models::OrderBook, String>> {
+        Box::pin(crate::public::KunaPublicClient::get_orderbook(self, coins))
+    }
+
+    fn get_markets(&self) -> BoxFuture<'_, Result<models::Markets, String>> {
+        Box::pin(crate::public::KunaPublicClient::get_markets(self))
+    }
+}
+
+impl Trading for crate::client::KunaClient {
+    fn get_balance(&self) -> BoxFuture<'_, Result<Vec<models::Currency>, String>> {
+        Box::pin(crate::client::KunaClient::get_balance(self))
+    }
+
+    fn create_order(
+        &self,
+        order: models::CreateOrder,
+    ) -> BoxFuture<'_, Result<Order, String>> {
+        Box::pin(crate::client::KunaClient::create_order(self, order))
+    }
+
+    fn delete_order(
+        &self,
+        cancel_order: models::CancelOrderRequest,
+    ) -> BoxFuture<'_, Result<Order, String>> {
+        Box::pin(crate::client::KunaClient::delete_order(self, cancel_order))
+    }
+
+    fn get_my_orders(&self, coins: Coins) -> BoxFuture<'_, Result<Vec<Order>, String>> {
+        Box::pin(crate::client::KunaClient::get_my_orders(self, coins))
+    }
+
+    fn get_order_history(&self, coins: Coins) -> BoxFuture<'_, Result<Vec<Order>, String>> {
+        Box::pin(crate::client::KunaClient::get_order_history(self, coins))
+    }
+}
+
+impl MarketData for crate::paper::PaperClient {
+    fn get_orderbook(&self, coins: Coins) -> BoxFuture<'_, Result<models::OrderBook, String>> {
+        Box::pin(self.public_client().get_orderbook(coins))
+    }
+
+    fn get_markets(&self) -> BoxFuture<'_, Result<models::Markets, String>> {
+        Box::pin(self.public_client().get_markets())
+    }
+}
+
+impl Trading for crate::paper::PaperClient {
+    fn get_balance(&self) -> BoxFuture<'_, Result<Vec<models::Currency>, String>> {
+        Box::pin(crate::paper::PaperClient::get_balance(self))
+    }
+
+    fn create_order(
+        &self,
+        order: models::CreateOrder,
+    ) -> BoxFuture<'_, Result<Order, String>> {
+        Box::pin(crate::paper::PaperClient::create_order(self, order))
+    }
+
+    fn delete_order(
+        &self,
+        cancel_order: models::CancelOrderRequest,
+    ) -> BoxFuture<'_, Result<Order, String>> {
+        Box::pin(crate::paper::PaperClient::delete_order(self, cancel_order))
+    }
+
+    fn get_my_orders(&self, coins: Coins) -> BoxFuture<'_, Result<Vec<Order>, String>> {
+        Box::pin(crate::paper::PaperClient::get_my_orders(self, coins))
+    }
+
+    fn get_order_history(&self, coins: Coins) -> BoxFuture<'_, Result<Vec<Order>, String>> {
+        Box::pin(crate::paper::PaperClient::get_order_history(self, coins))
+    }
+}
+
+impl<T: MarketData + ?Sized> MarketData for Arc<T> {
+    fn get_orderbook(&self, coins: Coins) -> BoxFuture<'_, Result<models::OrderBook, String>> {
+        (**self).get_orderbook(coins)
+    }
+
+    fn get_markets(&self) -> BoxFuture<'_, Result<models::Markets, String>> {
+        (**self).get_markets()
+    }
+}
+
+impl<T: Trading + ?Sized> Trading for Arc<T> {
+    fn get_balance(&self) -> BoxFuture<'_, Result<Vec<models::Currency>, String>> {
+        (**self).get_balance()
+    }
+
+    fn create_order(
+        &self,
+        order: models::CreateOrder,
+    ) -> BoxFuture<'_, Result<Order, String>> {
+        (**self).create_order(order)
+    }
+
+    fn delete_order(
+        &self,
+        cancel_order: models::CancelOrderRequest,
+    ) -> BoxFuture<'_, Result<Order, String>> {
+        (**self).delete_order(cancel_order)
+    }
+
+    fn get_my_orders(&self, coins: Coins) -> BoxFuture<'_, Result<Vec<Order>, String>> {
+        (**self).get_my_orders(coins)
+    }
+
+    fn get_order_history(&self, coins: Coins) -> BoxFuture<'_, Result<Vec<Order>, String>> {
+        (**self).get_order_history(coins)
+    }
+}
+
+/// Joins a market data source and a trading backend into one `Exchange`,
+/// e.g. a `KunaPublicClient` with a `KunaClient` for the same account.
+pub struct Combined<TMarketData, TTrading> {
+    pub market_data: TMarketData,
+    pub trading: TTrading,
+}
+
+impl<TMarketData, TTrading> Combined<TMarketData, TTrading> {
+    pub fn new(market_data: TMarketData, trading: TTrading) -> Combined<TMarketData, TTrading> {
+        Combined {
+            market_data,
+            trading,
+        }
+    }
+}
+
+impl<TMarketData: MarketData, TTrading: Send + Sync> MarketData for Combined<TMarketData, TTrading> {
+    fn get_orderbook(&self, coins: Coins) -> BoxFuture<'_, Result<models::OrderBook, String>> {
+        self.market_data.get_orderbook(coins)
+    }
+
+    fn get_markets(&self) -> BoxFuture<'_, Result<models::Markets, String>> {
+        self.market_data.get_markets()
+    }
+}
+
+impl<TMarketData: Send + Sync, TTrading: Trading> Trading for Combined<TMarketData, TTrading> {
+    fn get_balance(&self) -> BoxFuture<'_, Result<Vec<models::Currency>, String>> {
+        self.trading.get_balance()
+    }
+
+    fn create_order(
+        &self,
+        order: models::CreateOrder,
+    ) -> BoxFuture<'_, Result<Order, String>> {
+        self.trading.create_order(order)
+    }
+
+    fn delete_order(
+        &self,
+        cancel_order: models::CancelOrderRequest,
+    ) -> BoxFuture<'_, Result<Order, String>> {
+        self.trading.delete_order(cancel_order)
+    }
+
+    fn get_my_orders(&self, coins: Coins) -> BoxFuture<'_, Result<Vec<Order>, String>> {
+        self.trading.get_my_orders(coins)
+    }
+
+    fn get_order_history(&self, coins: Coins) -> BoxFuture<'_, Result<Vec<Order>, String>> {
+        self.trading.get_order_history(coins)
+    }
+}
+
+/// Logs every call made through the wrapped exchange together with its duration.
+pub struct Logged<T> {
+    inner: T,
+}
+
+impl<T> Logged<T> {
+    pub fn new(inner: T) -> Logged<T> {
+        Logged { inner }
+    }
+
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+async fn logged<TResult>(
+    name: &'static str,
+    future: BoxFuture<'_, Result<TResult, String>>,
+) -> Result<TResult, String> {
+    let started = std::time::Instant::now();
+    let result = future.await;
+    match &result {
+        Ok(_) => log::info!("{} succeeded in {:?}", name, started.elapsed()),
+        Err(error) => log::warn!("{} failed in {:?}: {}", name, started.elapsed(), error),
+    }
+    result
+}
+
+impl<T: MarketData> MarketData for Logged<T> {
+    fn get_orderbook(&self, coins: Coins) -> BoxFuture<'_, Result<models::OrderBook, String>> {
+        Box::pin(logged("get_orderbook", self.inner.get_orderbook(coins)))
+    }
+
+    fn get_markets(&self) -> BoxFuture<'_, Result<models::Markets, String>> {
+        Box::pin(logged("get_markets", self.inner.get_markets()))
+    }
+}
+
+impl<T: Trading> Trading for Logged<T> {
+    fn get_balance(&self) -> BoxFuture<'_, Result<Vec<models::Currency>, String>> {
+        Box::pin(logged("get_balance", self.inner.get_balance()))
+    }
+
+    fn create_order(
+        &self,
+        order: models::CreateOrder,
+    ) -> BoxFuture<'_, Result<Order, String>> {
+        log::info!("create_order: {:?}", order);
+        Box::pin(logged("create_order", self.inner.create_order(order)))
+    }
+
+    fn delete_order(
+        &self,
+        cancel_order: models::CancelOrderRequest,
+    ) -> BoxFuture<'_, Result<Order, String>> {
+        log::info!("delete_order: {:?}", cancel_order);
+        Box::pin(logged("delete_order", self.inner.delete_order(cancel_order)))
+    }
+
+    fn get_my_orders(&self, coins: Coins) -> BoxFuture<'_, Result<Vec<Order>, String>> {
+        Box::pin(logged("get_my_orders", self.inner.get_my_orders(coins)))
+    }
+
+    fn get_order_history(&self, coins: Coins) -> BoxFuture<'_, Result<Vec<Order>, String>> {
+        Box::pin(logged("get_order_history", self.inner.get_order_history(coins)))
+    }
+}
+
+/// Applies a `RateLimiter` in front of any exchange, including simulated ones.
+pub struct Throttled<T> {
+    inner: T,
+    rate_limiter: Arc<crate::limiter::RateLimiter>,
+}
+
+impl<T> Throttled<T> {
+    pub fn new(inner: T, rate_limiter: Arc<crate::limiter::RateLimiter>) -> Throttled<T> {
+        Throttled {
+            inner,
+            rate_limiter,
+        }
+    }
+
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<T: MarketData> MarketData for Throttled<T> {
+    fn get_orderbook(&self, coins: Coins) -> BoxFuture<'_, Result<models::OrderBook, String>> {
+        Box::pin(async move {
+            self.rate_limiter.acquire(crate::base::Endpoint::OrderBook).await;
+            self.inner.get_orderbook(coins).await
+        })
+    }
+
+    fn get_markets(&self) -> BoxFuture<'_, Result<models::Markets, String>> {
+        Box::pin(async move {
+            self.rate_limiter.acquire(crate::base::Endpoint::Markets).await;
+            self.inner.get_markets().await
+        })
+    }
+}
+
+impl<T: Trading> Trading for Throttled<T> {
+    fn get_balance(&self) -> BoxFuture<'_, Result<Vec<models::Currency>, String>> {
+        Box::pin(async move {
+            self.rate_limiter.acquire(crate::base::Endpoint::Balance).await;
+            self.inner.get_balance().await
+        })
+    }
+
+    fn create_order(
+        &self,
+        order: models::CreateOrder,
+    ) -> BoxFuture<'_, Result<Order, String>> {
+        Box::pin(async move {
+            self.rate_limiter.acquire(crate::base::Endpoint::CreateOrder).await;
+            self.inner.create_order(order).await
+        })
+    }
+
+    fn delete_order(
+        &self,
+        cancel_order: models::CancelOrderRequest,
+    ) -> BoxFuture<'_, Result<Order, String>> {
+        Box::pin(async move {
+            self.rate_limiter.acquire(crate::base::Endpoint::CancelOrder).await;
+            self.inner.delete_order(cancel_order).await
+        })
+    }
+
+    fn get_my_orders(&self, coins: Coins) -> BoxFuture<'_, Result<Vec<Order>, String>> {
+        Box::pin(async move {
+            self.rate_limiter.acquire(crate::base::Endpoint::MyOrders).await;
+            self.inner.get_my_orders(coins).await
+        })
+    }
+
+    fn get_order_history(&self, coins: Coins) -> BoxFuture<'_, Result<Vec<Order>, String>> {
+        Box::pin(async move {
+            self.rate_limiter.acquire(crate::base::Endpoint::OrderHistory).await;
+            self.inner.get_order_history(coins).await
+        })
+    }
+}
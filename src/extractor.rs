@@ -1,3 +1,4 @@
+#[cfg(feature = "hyper")]
 pub async fn read_body<TResult>(body: hyper::Body) -> Option<TResult>
 where
     TResult: serde::de::DeserializeOwned,
@@ -10,7 +11,14 @@ where
             return None;
         }
     };
-    match serde_json::from_slice(&bytes) {
+    parse_body(&bytes)
+}
+
+pub fn parse_body<TResult>(bytes: &[u8]) -> Option<TResult>
+where
+    TResult: serde::de::DeserializeOwned,
+{
+    match serde_json::from_slice(bytes) {
         Ok(result) => Some(result),
         Err(error) => {
             log::error!("Error on reading the body: {:#?}", error);
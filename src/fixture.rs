@@ -0,0 +1,243 @@
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+pub use crate::base::REDACTED;
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+pub struct Interaction {
+    pub method: String,
+    pub path: String,
+    pub request_headers: BTreeMap<String, String>,
+    pub request_body: String,
+    pub status: u16,
+    pub response_headers: BTreeMap<String, String>,
+    pub response_body: String,
+}
+
+impl Interaction {
+    pub fn matches(&self, method: &str, path: &str, body: &str) -> bool {
+        self.method == method && self.path == path && self.request_body == body
+    }
+}
+
+fn path_and_query(uri: &http::Uri) -> String {
+    uri.path_and_query()
+        .map(|path| path.to_string())
+        .unwrap_or_else(|| uri.path().to_owned())
+}
+
+fn headers_to_map(headers: &http::HeaderMap) -> BTreeMap<String, String> {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            // The nonce is redacted too so that fixtures do not change on every recording.
+            let value = if crate::base::is_sensitive_header(name) || name.as_str() == "kun-nonce" {
+                REDACTED.to_owned()
+            } else {
+                String::from_utf8_lossy(value.as_bytes()).into_owned()
+            };
+            (name.as_str().to_owned(), value)
+        })
+        .collect()
+}
+
+/// Collects every exchanged request/response pair, with credentials redacted, and writes
+/// them to a JSON file once, on `finish` or when dropped.
+#[derive(Debug)]
+pub struct Recorder {
+    path: std::path::PathBuf,
+    interactions: Mutex<Vec<Interaction>>,
+    // Interactions were recorded since the file was last written.
+    unsaved: std::sync::atomic::AtomicBool,
+}
+
+impl Recorder {
+    pub fn new<P: Into<std::path::PathBuf>>(path: P) -> Recorder {
+        Recorder {
+            path: path.into(),
+            interactions: Mutex::new(Vec::new()),
+            unsaved: std::sync::atomic::AtomicBool::new(false),
+        }
+    }
+
+    pub fn interactions(&self) -> Vec<Interaction> {
+        self.interactions
+            .lock()
+            .expect("Recorder lock is poisoned")
+            .clone()
+    }
+
+    pub fn record<TRequest, TResponse>(
+        &self,
+        request: &http::Request<TRequest>,
+        response: &http::Response<TResponse>,
+    ) where
+        TRequest: AsRef<[u8]>,
+        TResponse: AsRef<[u8]>,
+    {
+        let interaction = Interaction {
+            method: request.method().to_string(),
+            path: path_and_query(request.uri()),
+            request_headers: headers_to_map(request.headers()),
+            request_body: String::from_utf8_lossy(request.body().as_ref()).into_owned(),
+            status: response.status().as_u16(),
+            response_headers: headers_to_map(response.headers()),
+            response_body: String::from_utf8_lossy(response.body().as_ref()).into_owned(),
+        };
+        self.interactions
+            .lock()
+            .expect("Recorder lock is poisoned")
+            .push(interaction);
+        self.unsaved.store(true, std::sync::atomic::Ordering::SeqCst);
+    }
+
+    /// Writes the interactions recorded so far, replacing the file.
+    pub fn finish(&self) -> Result<(), String> {
+        let interactions = self.interactions.lock().expect("Recorder lock is poisoned");
+        let json = match serde_json::to_string_pretty(&*interactions) {
+            Ok(json) => json,
+            Err(error) => return Err(format!("Failed to serialize fixtures: {:#?}", error)),
+        };
+        match std::fs::write(&self.path, json) {
+            Ok(()) => {
+                self.unsaved.store(false, std::sync::atomic::Ordering::SeqCst);
+                Ok(())
+            },
+            Err(error) => Err(format!("Failed to write fixtures to {:?}: {:#?}", self.path, error)),
+        }
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        if self.unsaved.load(std::sync::atomic::Ordering::SeqCst) {
+            if let Err(error) = self.finish() {
+                log::error!("{}", error);
+            }
+        }
+    }
+}
+
+/// Serves recorded interactions back in order, failing on requests that were not recorded.
+#[derive(Debug)]
+pub struct Replayer {
+    interactions: Mutex<Vec<(Interaction, bool)>>,
+}
+
+impl Replayer {
+    pub fn new(interactions: Vec<Interaction>) -> Replayer {
+        Replayer {
+            interactions: Mutex::new(
+                interactions
+                    .into_iter()
+                    .map(|interaction| (interaction, false))
+                    .collect(),
+            ),
+        }
+    }
+
+    pub fn load<P: AsRef<std::path::Path>>(path: P) -> Result<Replayer, String> {
+        let json = match std::fs::read(path.as_ref()) {
+            Ok(json) => json,
+            Err(error) => return Err(format!("Failed to read fixtures from {:?}: {:#?}", path.as_ref(), error)),
+        };
+        match serde_json::from_slice::<Vec<Interaction>>(&json) {
+            Ok(interactions) => Ok(Replayer::new(interactions)),
+            Err(error) => Err(format!("Invalid fixtures file {:?}: {:#?}", path.as_ref(), error)),
+        }
+    }
+
+    pub fn unused(&self) -> Vec<Interaction> {
+        self.interactions
+            .lock()
+            .expect("Replayer lock is poisoned")
+            .iter()
+            .filter(|(_, used)| !used)
+            .map(|(interaction, _)| interaction.clone())
+            .collect()
+    }
+
+    pub fn replay<TRequest>(&self, request: &http::Request<TRequest>) -> Result<http::Response<Vec<u8>>, String>
+    where
+        TRequest: AsRef<[u8]>,
+    {
+        let method = request.method().to_string();
+        let path = path_and_query(request.uri());
+        let body = String::from_utf8_lossy(request.body().as_ref());
+        let mut interactions = self.interactions.lock().expect("Replayer lock is poisoned");
+        let interaction = match interactions
+            .iter_mut()
+            .find(|(interaction, used)| !used && interaction.matches(&method, &path, &body))
+        {
+            Some((interaction, used)) => {
+                *used = true;
+                interaction
+            },
+            None => return Err(format!("Unmatched request: {} {} {}", method, path, body)),
+        };
+        let mut response = http::Response::builder().status(interaction.status);
+        for (name, value) in interaction.response_headers.iter() {
+            response = response.header(name.as_str(), value.as_str());
+        }
+        response
+            .body(interaction.response_body.clone().into_bytes())
+            .map_err(|error| format!("Invalid recorded response: {:#?}", error))
+    }
+}
+
+/// Forwards requests to `inner` and records every exchange with `recorder`.
+pub struct RecordingTransport {
+    inner: std::sync::Arc<dyn crate::transport::Transport>,
+    recorder: std::sync::Arc<Recorder>,
+}
+
+impl RecordingTransport {
+    pub fn new(
+        inner: std::sync::Arc<dyn crate::transport::Transport>,
+        recorder: std::sync::Arc<Recorder>,
+    ) -> RecordingTransport {
+        RecordingTransport { inner, recorder }
+    }
+}
+
+impl crate::transport::Transport for RecordingTransport {
+    fn send(
+        &self,
+        request: crate::transport::Request,
+    ) -> crate::transport::BoxFuture<'_, Result<crate::transport::Response, crate::transport::TransportError>> {
+        Box::pin(async move {
+            let mut forward = http::Request::new(request.body().clone());
+            *forward.method_mut() = request.method().clone();
+            *forward.uri_mut() = request.uri().clone();
+            *forward.headers_mut() = request.headers().clone();
+            let response = self.inner.send(forward).await?;
+            self.recorder.record(&request, &response);
+            Ok(response)
+        })
+    }
+}
+
+/// Serves responses from a `Replayer` without touching the network.
+pub struct ReplayTransport {
+    replayer: std::sync::Arc<Replayer>,
+}
+
+impl ReplayTransport {
+    pub fn new(replayer: std::sync::Arc<Replayer>) -> ReplayTransport {
+        ReplayTransport { replayer }
+    }
+}
+
+impl crate::transport::Transport for ReplayTransport {
+    fn send(
+        &self,
+        request: crate::transport::Request,
+    ) -> crate::transport::BoxFuture<'_, Result<crate::transport::Response, crate::transport::TransportError>> {
+        // A mismatch is a broken fixture, retrying it could only hide that.
+        let response = self
+            .replayer
+            .replay(&request)
+            .map_err(crate::transport::TransportError::Rejected);
+        Box::pin(async move { response })
+    }
+}
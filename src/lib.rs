@@ -1,8 +1,25 @@
 pub mod context;
+pub mod credentials;
+pub mod signer;
 pub mod base;
 pub mod extractor;
 pub mod client;
 pub mod public;
 pub mod models;
+pub mod positional;
 pub mod coin;
 pub mod order;
+pub mod book;
+pub mod limiter;
+pub mod retry;
+pub mod middleware;
+pub mod response;
+pub mod fixture;
+pub mod paper;
+pub mod exchange;
+pub mod transport;
+pub mod builder;
+pub mod telemetry;
+mod dispatch;
+#[cfg(feature = "mock")]
+pub mod mock;
@@ -0,0 +1,356 @@
+use crate::base::Endpoint;
+use std::collections::HashMap;
+use std::time::Duration;
+use std::time::Instant;
+
+// Longest single sleep of `acquire`, the budget is checked again after it.
+const MAX_WAIT: Duration = Duration::from_secs(60);
+
+#[derive(Clone, Copy, Debug)]
+pub struct Budget {
+    capacity: f64,
+    refill_per_second: f64,
+}
+
+impl Budget {
+    /// Both values must be finite and positive.
+    pub fn new(capacity: f64, refill_per_second: f64) -> Result<Budget, String> {
+        if !(capacity.is_finite() && capacity > 0.0) {
+            return Err(format!("Invalid budget capacity {}, expected a positive number", capacity));
+        }
+        if !(refill_per_second.is_finite() && refill_per_second > 0.0) {
+            return Err(format!(
+                "Invalid budget refill rate {}, expected a positive number",
+                refill_per_second
+            ));
+        }
+        Ok(Budget {
+            capacity,
+            refill_per_second,
+        })
+    }
+
+    pub fn per_second(requests: f64) -> Result<Budget, String> {
+        Budget::new(requests, requests)
+    }
+
+    pub fn per_minute(requests: f64) -> Result<Budget, String> {
+        Budget::new(requests, requests / 60.0)
+    }
+
+    pub fn capacity(&self) -> f64 {
+        self.capacity
+    }
+
+    pub fn refill_per_second(&self) -> f64 {
+        self.refill_per_second
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct BudgetStatus {
+    pub available: f64,
+    pub capacity: f64,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    budget: Budget,
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl Bucket {
+    fn new(budget: Budget) -> Bucket {
+        Bucket {
+            budget,
+            tokens: budget.capacity,
+            last_refill: Instant::now(),
+        }
+    }
+
+    fn refill(&mut self) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.budget.refill_per_second)
+            .min(self.budget.capacity);
+        self.last_refill = now;
+    }
+
+    fn try_take(&mut self, weight: f64) -> Result<(), Duration> {
+        self.refill();
+        let weight = weight.min(self.budget.capacity);
+        if self.tokens >= weight {
+            self.tokens -= weight;
+            return Ok(());
+        }
+        let missing = weight - self.tokens;
+        match Duration::try_from_secs_f64(missing / self.budget.refill_per_second) {
+            Ok(wait) => Err(wait.min(MAX_WAIT)),
+            Err(_) => Err(MAX_WAIT),
+        }
+    }
+
+    fn status(&mut self) -> BudgetStatus {
+        self.refill();
+        BudgetStatus {
+            available: self.tokens,
+            capacity: self.budget.capacity,
+        }
+    }
+}
+
+/// Token bucket limiter with separate budgets for public and private endpoints.
+/// Share one instance between `KunaClient` and `KunaPublicClient` to keep a key within its limits.
+#[derive(Debug)]
+pub struct RateLimiter {
+    public: std::sync::Mutex<Bucket>,
+    private: std::sync::Mutex<Bucket>,
+    weights: HashMap<Endpoint, f64>,
+    // One queue per bucket, so waiting on one budget never holds back the other.
+    public_queue: tokio::sync::Mutex<()>,
+    private_queue: tokio::sync::Mutex<()>,
+}
+
+impl RateLimiter {
+    pub fn new(public: Budget, private: Budget) -> RateLimiter {
+        RateLimiter {
+            public: std::sync::Mutex::new(Bucket::new(public)),
+            private: std::sync::Mutex::new(Bucket::new(private)),
+            weights: HashMap::new(),
+            public_queue: tokio::sync::Mutex::new(()),
+            private_queue: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// `weight` must be finite and not negative, weights above a budget take all of it.
+    pub fn with_weight(mut self, endpoint: Endpoint, weight: f64) -> Result<RateLimiter, String> {
+        if !(weight.is_finite() && weight >= 0.0) {
+            return Err(format!("Invalid weight {} for {}, expected a number of at least 0", weight, endpoint));
+        }
+        self.weights.insert(endpoint, weight);
+        Ok(self)
+    }
+
+    pub fn weight(&self, endpoint: Endpoint) -> f64 {
+        self.weights.get(&endpoint).copied().unwrap_or(1.0)
+    }
+
+    fn bucket(&self, endpoint: Endpoint) -> &std::sync::Mutex<Bucket> {
+        if endpoint.is_private() {
+            &self.private
+        } else {
+            &self.public
+        }
+    }
+
+    fn queue(&self, endpoint: Endpoint) -> &tokio::sync::Mutex<()> {
+        if endpoint.is_private() {
+            &self.private_queue
+        } else {
+            &self.public_queue
+        }
+    }
+
+    /// Takes the endpoint weight from its budget or returns how long to wait before retrying.
+    pub fn try_acquire(&self, endpoint: Endpoint) -> Result<(), Duration> {
+        let weight = self.weight(endpoint);
+        self.bucket(endpoint)
+            .lock()
+            .expect("Rate limiter lock is poisoned")
+            .try_take(weight)
+    }
+
+    /// Waits until the endpoint weight fits into its budget.
+    /// Waiting callers of the same budget are served in the order they arrived.
+    pub async fn acquire(&self, endpoint: Endpoint) {
+        let _turn = self.queue(endpoint).lock().await;
+        while let Err(wait) = self.try_acquire(endpoint) {
+            log::debug!("Rate limit reached for {}, waiting {:?}", endpoint, wait);
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    pub fn public_budget(&self) -> BudgetStatus {
+        self.public
+            .lock()
+            .expect("Rate limiter lock is poisoned")
+            .status()
+    }
+
+    pub fn private_budget(&self) -> BudgetStatus {
+        self.private
+            .lock()
+            .expect("Rate limiter lock is poisoned")
+            .status()
+    }
+}
+
+/// Pause applied after a 429 that carried neither `retry-after` nor a reset time.
+pub const DEFAULT_RATE_LIMITED_PAUSE: Duration = Duration::from_secs(1);
+/// Upper bound of any pause, guarding against bogus reset headers.
+pub const MAX_RATE_LIMITED_PAUSE: Duration = Duration::from_secs(300);
+
+#[derive(Debug, Default)]
+struct Quota {
+    status: Option<crate::response::RateLimitStatus>,
+    paused_until: Option<Instant>,
+}
+
+/// Follows the quota reported by the exchange and holds requests back until its window
+/// resets once it is exhausted or a 429 was received. Public and private endpoints are
+/// tracked separately. Share one instance between the clients of a key, as the builder does.
+#[derive(Debug, Default)]
+pub struct QuotaTracker {
+    public: std::sync::Mutex<Quota>,
+    private: std::sync::Mutex<Quota>,
+}
+
+impl QuotaTracker {
+    pub fn new() -> QuotaTracker {
+        QuotaTracker::default()
+    }
+
+    fn quota(&self, endpoint: Endpoint) -> std::sync::MutexGuard<'_, Quota> {
+        let quota = if endpoint.is_private() {
+            &self.private
+        } else {
+            &self.public
+        };
+        quota.lock().expect("Quota tracker lock is poisoned")
+    }
+
+    /// Records the quota headers of a response, `rate_limited` being whether it was a 429.
+    pub fn observe(
+        &self,
+        endpoint: Endpoint,
+        status: Option<crate::response::RateLimitStatus>,
+        rate_limited: bool,
+    ) {
+        let pause = match (status.and_then(|status| status.pause()), rate_limited) {
+            (Some(pause), _) => Some(pause),
+            (None, true) => Some(status.and_then(|status| status.reset).unwrap_or(DEFAULT_RATE_LIMITED_PAUSE)),
+            (None, false) => None,
+        };
+        let mut quota = self.quota(endpoint);
+        if status.is_some() {
+            quota.status = status;
+        }
+        if let Some(pause) = pause {
+            let until = Instant::now() + pause.min(MAX_RATE_LIMITED_PAUSE);
+            log::warn!("Rate limit reached for {}, pausing requests for {:?}", endpoint, pause);
+            quota.paused_until = Some(quota.paused_until.map_or(until, |paused_until| paused_until.max(until)));
+        }
+    }
+
+    /// The last quota reported for the endpoint group, `None` before any response carried it.
+    pub fn status(&self, endpoint: Endpoint) -> Option<crate::response::RateLimitStatus> {
+        self.quota(endpoint).status
+    }
+
+    /// How long requests to the endpoint are still held back.
+    pub fn paused_for(&self, endpoint: Endpoint) -> Option<Duration> {
+        let paused_until = self.quota(endpoint).paused_until?;
+        paused_until.checked_duration_since(Instant::now()).filter(|pause| !pause.is_zero())
+    }
+
+    /// Waits until the endpoint is no longer paused.
+    pub async fn wait(&self, endpoint: Endpoint) {
+        while let Some(pause) = self.paused_for(endpoint) {
+            log::debug!("{} is paused by the exchange rate limit, waiting {:?}", endpoint, pause);
+            tokio::time::sleep(pause).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn waiting_public_call_does_not_block_private_calls() {
+        let limiter = std::sync::Arc::new(RateLimiter::new(
+            Budget::new(1.0, 0.001).expect("Invalid budget"),
+            Budget::per_second(10.0).expect("Invalid budget"),
+        ));
+        limiter.acquire(Endpoint::Markets).await;
+        let waiting = tokio::spawn({
+            let limiter = limiter.clone();
+            async move { limiter.acquire(Endpoint::Markets).await }
+        });
+        tokio::task::yield_now().await;
+        assert!(!waiting.is_finished());
+        let private = tokio::time::timeout(Duration::from_millis(10), limiter.acquire(Endpoint::Balance));
+        assert!(private.await.is_ok());
+        waiting.abort();
+    }
+
+    #[test]
+    fn budgets_and_weights_are_validated() {
+        let invalid = [
+            (0.0, 1.0),
+            (-1.0, 1.0),
+            (f64::NAN, 1.0),
+            (f64::INFINITY, 1.0),
+            (1.0, 0.0),
+            (1.0, -1.0),
+            (1.0, f64::NAN),
+        ];
+        for (capacity, refill) in invalid {
+            assert!(Budget::new(capacity, refill).is_err(), "{} {}", capacity, refill);
+        }
+        assert!(Budget::per_minute(f64::NAN).is_err());
+        let budget = Budget::per_minute(60.0).expect("Invalid budget");
+        assert_eq!(budget.capacity(), 60.0);
+        assert_eq!(budget.refill_per_second(), 1.0);
+
+        for weight in [-1.0, f64::NAN, f64::INFINITY] {
+            assert!(RateLimiter::new(budget, budget).with_weight(Endpoint::Markets, weight).is_err());
+        }
+        let limiter = RateLimiter::new(budget, budget).with_weight(Endpoint::Markets, 0.0).expect("Invalid weight");
+        assert_eq!(limiter.weight(Endpoint::Markets), 0.0);
+        assert_eq!(limiter.weight(Endpoint::Balance), 1.0);
+    }
+
+    #[test]
+    fn budget_is_taken_by_weight_and_refilled_over_time() {
+        let limiter = RateLimiter::new(
+            Budget::new(2.0, 20.0).expect("Invalid budget"),
+            Budget::new(3.0, 1.0).expect("Invalid budget"),
+        )
+        .with_weight(Endpoint::CreateOrder, 2.0)
+        .expect("Invalid weight")
+        .with_weight(Endpoint::CancelOrder, 10.0)
+        .expect("Invalid weight");
+
+        assert_eq!(limiter.try_acquire(Endpoint::Markets), Ok(()));
+        assert_eq!(limiter.try_acquire(Endpoint::Markets), Ok(()));
+        let wait = limiter.try_acquire(Endpoint::Markets).unwrap_err();
+        assert!(wait > Duration::ZERO && wait <= Duration::from_millis(50), "{:?}", wait);
+        std::thread::sleep(wait + Duration::from_millis(10));
+        assert_eq!(limiter.try_acquire(Endpoint::Markets), Ok(()));
+        assert!(limiter.public_budget().available < 1.0);
+
+        // The private budget is untouched by public calls and weights apply to it.
+        assert_eq!(limiter.try_acquire(Endpoint::CreateOrder), Ok(()));
+        assert!((limiter.private_budget().available - 1.0).abs() < 0.1);
+        assert!(limiter.try_acquire(Endpoint::CreateOrder).is_err());
+        // A weight above the capacity waits for a full budget instead of forever.
+        let wait = limiter.try_acquire(Endpoint::CancelOrder).unwrap_err();
+        assert!(wait > Duration::from_secs(1) && wait <= Duration::from_secs(2), "{:?}", wait);
+    }
+
+    #[test]
+    fn wait_for_a_tiny_refill_rate_is_bounded() {
+        let limiter = RateLimiter::new(
+            Budget::new(1.0, 1e-300).expect("Invalid budget"),
+            Budget::new(f64::MAX, f64::MIN_POSITIVE).expect("Invalid budget"),
+        )
+        .with_weight(Endpoint::Balance, f64::MAX)
+        .expect("Invalid weight");
+        assert_eq!(limiter.try_acquire(Endpoint::Markets), Ok(()));
+        assert_eq!(limiter.try_acquire(Endpoint::Markets), Err(MAX_WAIT));
+        assert_eq!(limiter.try_acquire(Endpoint::Balance), Ok(()));
+        assert_eq!(limiter.try_acquire(Endpoint::Balance), Err(MAX_WAIT));
+    }
+}
@@ -0,0 +1,137 @@
+use crate::base::Endpoint;
+use std::sync::Arc;
+
+/// Hooks around every request of a client, attempts and retries included.
+///
+/// `before_send` runs after the client has set the method, url and its own headers and
+/// before the request is signed, so added headers and the body reach the exchange as is.
+/// `after_receive` runs for every response that arrived, whatever its status.
+pub trait Middleware: Send + Sync {
+    fn before_send(
+        &self,
+        _endpoint: Endpoint,
+        builder: http::request::Builder,
+        _body: &[u8],
+    ) -> http::request::Builder {
+        builder
+    }
+
+    fn after_receive(
+        &self,
+        _endpoint: Endpoint,
+        _status: http::StatusCode,
+        _headers: &http::HeaderMap,
+        _body: &[u8],
+    ) {
+    }
+}
+
+impl<T: Middleware + ?Sized> Middleware for Arc<T> {
+    fn before_send(
+        &self,
+        endpoint: Endpoint,
+        builder: http::request::Builder,
+        body: &[u8],
+    ) -> http::request::Builder {
+        (**self).before_send(endpoint, builder, body)
+    }
+
+    fn after_receive(
+        &self,
+        endpoint: Endpoint,
+        status: http::StatusCode,
+        headers: &http::HeaderMap,
+        body: &[u8],
+    ) {
+        (**self).after_receive(endpoint, status, headers, body)
+    }
+}
+
+/// Runs its middleware in the order they were added, for both hooks.
+#[derive(Clone, Default)]
+pub struct MiddlewareChain {
+    middleware: Vec<Arc<dyn Middleware>>,
+}
+
+impl MiddlewareChain {
+    pub fn new() -> MiddlewareChain {
+        MiddlewareChain::default()
+    }
+
+    pub fn with(mut self, middleware: Arc<dyn Middleware>) -> MiddlewareChain {
+        self.middleware.push(middleware);
+        self
+    }
+
+    pub fn push(&mut self, middleware: Arc<dyn Middleware>) {
+        self.middleware.push(middleware);
+    }
+
+    pub fn len(&self) -> usize {
+        self.middleware.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.middleware.is_empty()
+    }
+}
+
+impl Middleware for MiddlewareChain {
+    fn before_send(
+        &self,
+        endpoint: Endpoint,
+        builder: http::request::Builder,
+        body: &[u8],
+    ) -> http::request::Builder {
+        self.middleware
+            .iter()
+            .fold(builder, |builder, middleware| middleware.before_send(endpoint, builder, body))
+    }
+
+    fn after_receive(
+        &self,
+        endpoint: Endpoint,
+        status: http::StatusCode,
+        headers: &http::HeaderMap,
+        body: &[u8],
+    ) {
+        for middleware in self.middleware.iter() {
+            middleware.after_receive(endpoint, status, headers, body);
+        }
+    }
+}
+
+impl std::fmt::Debug for MiddlewareChain {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        formatter
+            .debug_struct("MiddlewareChain")
+            .field("len", &self.middleware.len())
+            .finish()
+    }
+}
+
+/// Sets a header on every request, replacing the value set by the client if any.
+pub struct HeaderMiddleware {
+    name: http::header::HeaderName,
+    value: http::HeaderValue,
+}
+
+impl HeaderMiddleware {
+    pub fn new(name: http::header::HeaderName, value: http::HeaderValue) -> HeaderMiddleware {
+        HeaderMiddleware { name, value }
+    }
+}
+
+impl Middleware for HeaderMiddleware {
+    fn before_send(
+        &self,
+        _endpoint: Endpoint,
+        mut builder: http::request::Builder,
+        _body: &[u8],
+    ) -> http::request::Builder {
+        if let Some(headers) = builder.headers_mut() {
+            headers.insert(self.name.clone(), self.value.clone());
+        }
+        builder
+    }
+}
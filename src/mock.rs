@@ -0,0 +1,696 @@
+use crate::base;
+use crate::base::Side;
+use crate::base::Target;
+use crate::coin::Coin;
+use crate::coin::Coins;
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+
+const EPSILON: f64 = 1e-12;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MockOrderState {
+    Active,
+    PartiallyFilled,
+    Executed,
+    Canceled,
+}
+
+impl MockOrderState {
+    fn is_open(&self) -> bool {
+        matches!(self, MockOrderState::Active | MockOrderState::PartiallyFilled)
+    }
+}
+
+impl std::fmt::Display for MockOrderState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MockOrderState::Active => write!(f, "ACTIVE"),
+            MockOrderState::PartiallyFilled => write!(f, "PARTIALLY FILLED"),
+            MockOrderState::Executed => write!(f, "EXECUTED"),
+            MockOrderState::Canceled => write!(f, "CANCELED"),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct MockOrder {
+    pub id: i32,
+    pub coins: Coins,
+    pub side: Side,
+    pub target: Target,
+    pub price: f64,
+    pub original_amount: f64,
+    pub remaining_amount: f64,
+    pub notional: f64,
+    pub state: MockOrderState,
+    pub created_at: i64,
+    pub updated_at: i64,
+    // Orders placed through the API, as opposed to liquidity seeded by the test.
+    pub own: bool,
+}
+
+impl MockOrder {
+    pub fn executed_amount(&self) -> f64 {
+        self.original_amount - self.remaining_amount
+    }
+
+    pub fn average_price(&self) -> f64 {
+        let executed = self.executed_amount();
+        if executed > EPSILON {
+            self.notional / executed
+        } else {
+            0.0
+        }
+    }
+
+    fn signed(&self, amount: f64) -> f64 {
+        match self.side {
+            Side::Buy => amount,
+            Side::Sell => -amount,
+        }
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        serde_json::json!([
+            self.id,
+            null,
+            null,
+            self.coins.to_string(),
+            self.created_at,
+            self.updated_at,
+            self.signed(self.remaining_amount).to_string(),
+            self.signed(self.original_amount).to_string(),
+            self.target.to_string().to_uppercase(),
+            null,
+            null,
+            null,
+            null,
+            self.state.to_string(),
+            null,
+            null,
+            self.price.to_string(),
+            self.average_price().to_string(),
+        ])
+    }
+
+    fn to_canceled_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "id": self.id,
+            "side": match self.side { Side::Buy => "buy", Side::Sell => "sell" },
+            "type": self.target.to_string(),
+            "price": self.price.to_string(),
+            "avg_execution_price": self.average_price().to_string(),
+            "state": self.state.to_string().to_lowercase(),
+            "symbol": self.coins.to_string(),
+            "timestamp": self.updated_at,
+            "original_amount": self.original_amount.to_string(),
+            "remaining_amount": self.remaining_amount.to_string(),
+            "executed_amount": self.executed_amount().to_string(),
+            "is_cancelled": null,
+            "is_hidden": null,
+            "is_live": null,
+            "was_forced": null,
+            "exchange": null,
+        })
+    }
+}
+
+#[derive(Debug)]
+struct Failure {
+    // Only requests to this path are affected, any request if `None`.
+    path: Option<String>,
+    status: http::StatusCode,
+    headers: http::HeaderMap,
+    body: String,
+    // The request is still handled, only its response is replaced.
+    handled: bool,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    markets: Vec<crate::models::Market>,
+    balances: HashMap<Coin, f64>,
+    orders: Vec<MockOrder>,
+    next_id: i32,
+    latency: Duration,
+    failures: VecDeque<Failure>,
+    response_headers: http::HeaderMap,
+}
+
+impl State {
+    fn next_id(&mut self) -> i32 {
+        self.next_id += 1;
+        self.next_id
+    }
+
+    fn balance(&self, coin: &Coin) -> f64 {
+        self.balances.get(coin).copied().unwrap_or(0.0)
+    }
+
+    fn reserved(&self, coin: &Coin) -> f64 {
+        self.orders
+            .iter()
+            .filter(|order| order.own && order.state.is_open())
+            .map(|order| match order.side {
+                Side::Buy if &order.coins.quote_coin() == coin => order.remaining_amount * order.price,
+                Side::Sell if &order.coins.base_coin() == coin => order.remaining_amount,
+                _ => 0.0,
+            })
+            .sum()
+    }
+
+    fn available(&self, coin: &Coin) -> f64 {
+        self.balance(coin) - self.reserved(coin)
+    }
+
+    fn settle(&mut self, coins: &Coins, side: Side, amount: f64, price: f64) {
+        let (base_delta, quote_delta) = match side {
+            Side::Buy => (amount, -amount * price),
+            Side::Sell => (-amount, amount * price),
+        };
+        *self.balances.entry(coins.base_coin()).or_insert(0.0) += base_delta;
+        *self.balances.entry(coins.quote_coin()).or_insert(0.0) += quote_delta;
+    }
+
+    fn book_levels(&self, coins: &Coins) -> Vec<(f64, f64, u32)> {
+        let mut levels: Vec<(f64, f64, u32)> = Vec::new();
+        for order in self.orders.iter().filter(|order| {
+            &order.coins == coins && order.state.is_open() && order.target == Target::Limit
+        }) {
+            let amount = order.signed(order.remaining_amount);
+            match levels
+                .iter_mut()
+                .find(|level| level.0 == order.price && (level.1 > 0.0) == (amount > 0.0))
+            {
+                Some(level) => {
+                    level.1 += amount;
+                    level.2 += 1;
+                },
+                None => levels.push((order.price, amount, 1)),
+            }
+        }
+        levels.sort_by(|left, right| {
+            right.1.signum().partial_cmp(&left.1.signum()).unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| match left.1 > 0.0 {
+                    true => right.0.partial_cmp(&left.0).unwrap_or(std::cmp::Ordering::Equal),
+                    false => left.0.partial_cmp(&right.0).unwrap_or(std::cmp::Ordering::Equal),
+                })
+        });
+        levels
+    }
+
+    // Fills resting orders crossed by `incoming` in price-time priority and returns the fills.
+    fn match_order(&mut self, incoming: &mut MockOrder) -> Vec<(usize, f64, f64)> {
+        let mut candidates: Vec<usize> = self
+            .orders
+            .iter()
+            .enumerate()
+            .filter(|(_, order)| {
+                order.coins == incoming.coins
+                    && order.state.is_open()
+                    && order.target == Target::Limit
+                    && !(order.own && incoming.own)
+                    && match (incoming.side, order.side) {
+                        (Side::Buy, Side::Sell) => {
+                            incoming.target == Target::Market || order.price <= incoming.price
+                        },
+                        (Side::Sell, Side::Buy) => {
+                            incoming.target == Target::Market || order.price >= incoming.price
+                        },
+                        _ => false,
+                    }
+            })
+            .map(|(index, _)| index)
+            .collect();
+        candidates.sort_by(|left, right| {
+            let (left, right) = (&self.orders[*left], &self.orders[*right]);
+            let by_price = match incoming.side {
+                Side::Buy => left.price.partial_cmp(&right.price),
+                Side::Sell => right.price.partial_cmp(&left.price),
+            };
+            by_price
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then(left.id.cmp(&right.id))
+        });
+        let mut fills = Vec::new();
+        for index in candidates {
+            if incoming.remaining_amount <= EPSILON {
+                break;
+            }
+            let resting = &self.orders[index];
+            let amount = incoming.remaining_amount.min(resting.remaining_amount);
+            fills.push((index, amount, resting.price));
+            incoming.remaining_amount -= amount;
+            incoming.notional += amount * resting.price;
+        }
+        fills
+    }
+
+    fn apply_fills(&mut self, fills: &[(usize, f64, f64)], timestamp: i64) {
+        for (index, amount, price) in fills.iter().copied() {
+            let resting = &mut self.orders[index];
+            resting.remaining_amount -= amount;
+            resting.notional += amount * price;
+            resting.updated_at = timestamp;
+            resting.state = if resting.remaining_amount <= EPSILON {
+                MockOrderState::Executed
+            } else {
+                MockOrderState::PartiallyFilled
+            };
+            let (own, coins, side) = (resting.own, resting.coins.clone(), resting.side);
+            if own {
+                self.settle(&coins, side, amount, price);
+            }
+        }
+    }
+
+    fn place(&mut self, mut order: MockOrder) -> Result<MockOrder, String> {
+        let fills = self.match_order(&mut order);
+        if order.own {
+            let (coin, required) = match order.side {
+                Side::Buy => (
+                    order.coins.quote_coin(),
+                    order.notional
+                        + match order.target {
+                            Target::Limit => order.remaining_amount * order.price,
+                            Target::Market => 0.0,
+                        },
+                ),
+                Side::Sell => (order.coins.base_coin(), order.original_amount),
+            };
+            if self.available(&coin) + EPSILON < required {
+                return Err(format!("insufficient_funds: {} required {}", coin, required));
+            }
+        }
+        self.apply_fills(&fills, order.created_at);
+        if order.own {
+            let executed = order.executed_amount();
+            if executed > EPSILON {
+                self.settle(&order.coins, order.side, executed, order.notional / executed);
+            }
+        }
+        order.state = match (order.remaining_amount <= EPSILON, order.executed_amount() > EPSILON) {
+            (true, _) => MockOrderState::Executed,
+            (false, _) if order.target == Target::Market => MockOrderState::Canceled,
+            (false, true) => MockOrderState::PartiallyFilled,
+            (false, false) => MockOrderState::Active,
+        };
+        self.orders.push(order.clone());
+        Ok(order)
+    }
+}
+
+/// In-memory exchange state served by `MockServer`.
+pub struct MockExchange {
+    public_key: String,
+    signer: crate::signer::HmacSigner,
+    state: Mutex<State>,
+}
+
+impl MockExchange {
+    pub fn new(public_key: &str, private_key: &str) -> MockExchange {
+        MockExchange {
+            public_key: public_key.to_owned(),
+            signer: crate::signer::HmacSigner::new(private_key.to_owned()),
+            state: Mutex::new(State::default()),
+        }
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().expect("Mock exchange lock is poisoned")
+    }
+
+    pub fn add_market(&self, market: crate::models::Market) {
+        self.state().markets.push(market);
+    }
+
+    pub fn set_balance(&self, coin: Coin, amount: f64) {
+        self.state().balances.insert(coin, amount);
+    }
+
+    pub fn balance(&self, coin: &Coin) -> f64 {
+        self.state().balance(coin)
+    }
+
+    /// Places a resting limit order that does not belong to the API user.
+    pub fn add_liquidity(&self, coins: Coins, side: Side, price: f64, amount: f64) {
+        let mut state = self.state();
+        let timestamp = chrono::Utc::now().timestamp_millis();
+        let order = MockOrder {
+            id: state.next_id(),
+            coins,
+            side,
+            target: Target::Limit,
+            price,
+            original_amount: amount,
+            remaining_amount: amount,
+            notional: 0.0,
+            state: MockOrderState::Active,
+            created_at: timestamp,
+            updated_at: timestamp,
+            own: false,
+        };
+        let _ = state.place(order);
+    }
+
+    pub fn orders(&self) -> Vec<MockOrder> {
+        self.state()
+            .orders
+            .iter()
+            .filter(|order| order.own)
+            .cloned()
+            .collect()
+    }
+
+    pub fn set_latency(&self, latency: Duration) {
+        self.state().latency = latency;
+    }
+
+    /// Adds `headers` to every following response, e.g. the quota headers of the exchange.
+    pub fn set_response_headers(&self, headers: http::HeaderMap) {
+        self.state().response_headers = headers;
+    }
+
+    fn push_failure(&self, path: Option<&str>, status: http::StatusCode, body: &str, handled: bool) {
+        self.push_failure_with(path, status, http::HeaderMap::new(), body, handled);
+    }
+
+    fn push_failure_with(
+        &self,
+        path: Option<&str>,
+        status: http::StatusCode,
+        headers: http::HeaderMap,
+        body: &str,
+        handled: bool,
+    ) {
+        self.state().failures.push_back(Failure {
+            path: path.map(str::to_owned),
+            status,
+            headers,
+            body: body.to_owned(),
+            handled,
+        });
+    }
+
+    /// Answers the next request with `status` and `body` instead of handling it.
+    pub fn fail_next(&self, status: http::StatusCode, body: &str) {
+        self.push_failure(None, status, body, false);
+    }
+
+    /// Like `fail_next` with extra response headers, e.g. a 429 with `retry-after`.
+    pub fn fail_next_with(&self, status: http::StatusCode, headers: http::HeaderMap, body: &str) {
+        self.push_failure_with(None, status, headers, body, false);
+    }
+
+    /// Like `fail_next` for the next request to `path`, e.g. `/v3/auth/w/order/submit`.
+    pub fn fail_next_on(&self, path: &str, status: http::StatusCode, body: &str) {
+        self.push_failure(Some(path), status, body, false);
+    }
+
+    /// Handles the next request to `path` but answers with `status` and `body`,
+    /// as if the response was lost on its way back.
+    pub fn lose_next_response_on(&self, path: &str, status: http::StatusCode, body: &str) {
+        self.push_failure(Some(path), status, body, true);
+    }
+
+    async fn handle(&self, request: http::Request<hyper::Body>) -> http::Response<hyper::Body> {
+        let path = request.uri().path().to_owned();
+        let (latency, failure, mut headers) = {
+            let mut state = self.state();
+            let failure = state
+                .failures
+                .iter()
+                .position(|failure| failure.path.as_ref().is_none_or(|failure_path| failure_path == &path))
+                .and_then(|index| state.failures.remove(index));
+            (state.latency, failure, state.response_headers.clone())
+        };
+        if !latency.is_zero() {
+            tokio::time::sleep(latency).await;
+        }
+        let mut response = match failure {
+            Some(failure) => {
+                if failure.handled {
+                    let _ = self.route(request, path).await;
+                }
+                headers.extend(failure.headers);
+                response(failure.status, failure.body)
+            },
+            None => self.route(request, path).await,
+        };
+        response.headers_mut().extend(headers);
+        response
+    }
+
+    async fn route(&self, request: http::Request<hyper::Body>, path: String) -> http::Response<hyper::Body> {
+        let (parts, body) = request.into_parts();
+        let body = match hyper::body::to_bytes(body).await {
+            Ok(body) => String::from_utf8_lossy(&body).into_owned(),
+            Err(error) => return error_response(http::StatusCode::BAD_REQUEST, &error.to_string()),
+        };
+        let segments: Vec<&str> = path.trim_start_matches('/').split('/').collect();
+        match (&parts.method, segments.as_slice()) {
+            (&http::Method::GET, [base::VERSION, base::BOOK, symbol]) => self.book(symbol),
+            (&http::Method::GET, [base::VERSION, base::MARKETS]) => self.markets(),
+            (&http::Method::POST, segments) => {
+                if let Err(error) = self.authenticate(&parts.headers, &path, &body) {
+                    return error_response(http::StatusCode::UNAUTHORIZED, &error);
+                }
+                match segments {
+                    [base::VERSION, base::AUTH, base::REQUEST, base::WALLETS] => self.wallets(),
+                    [base::VERSION, base::AUTH, base::W, base::ORDER, base::SUBMIT] => self.submit(&body),
+                    [base::VERSION, base::ORDER, base::CANCEL] => self.cancel(&body),
+                    [base::VERSION, base::AUTH, base::REQUEST, base::ORDERS, symbol] => self.orders_json(symbol, true),
+                    [base::VERSION, base::AUTH, base::REQUEST, base::ORDERS, symbol, base::HISTORY] => {
+                        self.orders_json(symbol, false)
+                    },
+                    _ => error_response(http::StatusCode::NOT_FOUND, "not_found"),
+                }
+            },
+            _ => error_response(http::StatusCode::NOT_FOUND, "not_found"),
+        }
+    }
+
+    fn authenticate(&self, headers: &http::HeaderMap, path: &str, body: &str) -> Result<(), String> {
+        let header = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .ok_or(format!("missing_header: {}", name))
+        };
+        if header("kun-apikey")? != self.public_key {
+            return Err("invalid_api_key".to_owned());
+        }
+        match self.signer.verify_headers(path, headers, body.as_bytes(), crate::signer::DEFAULT_NONCE_WINDOW) {
+            Ok(()) => Ok(()),
+            Err(crate::signer::VerificationError::MissingHeader(name)) => Err(format!("missing_header: {}", name)),
+            Err(crate::signer::VerificationError::InvalidSignature) => Err("signature_invalid".to_owned()),
+            Err(_) => Err("nonce_invalid".to_owned()),
+        }
+    }
+
+    fn book(&self, symbol: &str) -> http::Response<hyper::Body> {
+        let coins = match Coins::try_from(symbol) {
+            Ok(coins) => coins,
+            Err(error) => return error_response(http::StatusCode::NOT_FOUND, error),
+        };
+        json_response(serde_json::json!(self.state().book_levels(&coins)))
+    }
+
+    fn markets(&self) -> http::Response<hyper::Body> {
+        let markets: Vec<_> = self
+            .state()
+            .markets
+            .iter()
+            .map(|market| {
+                serde_json::json!({
+                    "id": market.id,
+                    "base_unit": market.base_unit,
+                    "quote_unit": market.quote_unit,
+                    "base_precision": market.base_precision,
+                    "quote_precision": market.quote_precision,
+                    "display_precision": market.display_precision,
+                    "price_change": market.price_change,
+                })
+            })
+            .collect();
+        json_response(serde_json::Value::Array(markets))
+    }
+
+    fn wallets(&self) -> http::Response<hyper::Body> {
+        let state = self.state();
+        let wallets: Vec<_> = state
+            .balances
+            .keys()
+            .map(|coin| {
+                serde_json::json!([
+                    "exchange",
+                    coin.to_string(),
+                    state.balance(coin),
+                    null,
+                    state.available(coin),
+                ])
+            })
+            .collect();
+        json_response(serde_json::Value::Array(wallets))
+    }
+
+    fn submit(&self, body: &str) -> http::Response<hyper::Body> {
+        let request: serde_json::Value = match serde_json::from_str(body) {
+            Ok(request) => request,
+            Err(error) => return error_response(http::StatusCode::BAD_REQUEST, &error.to_string()),
+        };
+        let coins = match request["symbol"].as_str().map(Coins::try_from) {
+            Some(Ok(coins)) => coins,
+            _ => return error_response(http::StatusCode::BAD_REQUEST, "invalid_symbol"),
+        };
+        let target = match request["type"].as_str().map(Target::try_from) {
+            Some(Ok(target)) => target,
+            _ => return error_response(http::StatusCode::BAD_REQUEST, "invalid_type"),
+        };
+        let amount = match request["amount"].as_f64() {
+            Some(amount) if amount != 0.0 => amount,
+            _ => return error_response(http::StatusCode::BAD_REQUEST, "invalid_amount"),
+        };
+        let price = request["price"].as_f64().unwrap_or(0.0);
+        if target == Target::Limit && price <= 0.0 {
+            return error_response(http::StatusCode::BAD_REQUEST, "invalid_price");
+        }
+        let mut state = self.state();
+        let timestamp = chrono::Utc::now().timestamp_millis();
+        let order = MockOrder {
+            id: state.next_id(),
+            coins,
+            side: if amount < 0.0 { Side::Sell } else { Side::Buy },
+            target,
+            price,
+            original_amount: amount.abs(),
+            remaining_amount: amount.abs(),
+            notional: 0.0,
+            state: MockOrderState::Active,
+            created_at: timestamp,
+            updated_at: timestamp,
+            own: true,
+        };
+        match state.place(order) {
+            Ok(order) => json_response(order.to_json()),
+            Err(error) => error_response(http::StatusCode::BAD_REQUEST, &error),
+        }
+    }
+
+    fn cancel(&self, body: &str) -> http::Response<hyper::Body> {
+        let request: serde_json::Value = match serde_json::from_str(body) {
+            Ok(request) => request,
+            Err(error) => return error_response(http::StatusCode::BAD_REQUEST, &error.to_string()),
+        };
+        let order_id = match request["order_id"].as_i64() {
+            Some(order_id) => order_id as i32,
+            None => return error_response(http::StatusCode::BAD_REQUEST, "invalid_order_id"),
+        };
+        let mut state = self.state();
+        let order = match state
+            .orders
+            .iter_mut()
+            .find(|order| order.own && order.id == order_id && order.state.is_open())
+        {
+            Some(order) => order,
+            None => return error_response(http::StatusCode::NOT_FOUND, "order_not_found"),
+        };
+        order.state = MockOrderState::Canceled;
+        order.updated_at = chrono::Utc::now().timestamp_millis();
+        json_response(order.to_canceled_json())
+    }
+
+    // Open orders of `symbol`, or the closed ones for the history route.
+    fn orders_json(&self, symbol: &str, open: bool) -> http::Response<hyper::Body> {
+        let coins = match Coins::try_from(symbol) {
+            Ok(coins) => coins,
+            Err(error) => return error_response(http::StatusCode::NOT_FOUND, error),
+        };
+        let orders: Vec<_> = self
+            .state()
+            .orders
+            .iter()
+            .filter(|order| order.own && order.coins == coins && order.state.is_open() == open)
+            .map(MockOrder::to_json)
+            .collect();
+        json_response(serde_json::Value::Array(orders))
+    }
+}
+
+fn response(status: http::StatusCode, body: String) -> http::Response<hyper::Body> {
+    http::Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        .body(hyper::Body::from(body))
+        .expect("Invalid mock response")
+}
+
+fn json_response(body: serde_json::Value) -> http::Response<hyper::Body> {
+    response(http::StatusCode::OK, body.to_string())
+}
+
+fn error_response(status: http::StatusCode, message: &str) -> http::Response<hyper::Body> {
+    response(status, serde_json::json!({ "messages": [message] }).to_string())
+}
+
+/// Serves a `MockExchange` over HTTP on a random local port until dropped.
+pub struct MockServer {
+    url: url::Url,
+    exchange: Arc<MockExchange>,
+    shutdown: Option<tokio::sync::oneshot::Sender<()>>,
+}
+
+impl MockServer {
+    pub async fn start(exchange: MockExchange) -> Result<MockServer, String> {
+        let exchange = Arc::new(exchange);
+        let service_exchange = exchange.clone();
+        let make_service = hyper::service::make_service_fn(move |_| {
+            let exchange = service_exchange.clone();
+            async move {
+                Ok::<_, std::convert::Infallible>(hyper::service::service_fn(move |request| {
+                    let exchange = exchange.clone();
+                    async move { Ok::<_, std::convert::Infallible>(exchange.handle(request).await) }
+                }))
+            }
+        });
+        let address = std::net::SocketAddr::from(([127, 0, 0, 1], 0));
+        let server = match hyper::Server::try_bind(&address) {
+            Ok(server) => server.serve(make_service),
+            Err(error) => return Err(format!("Failed to bind mock server: {:#?}", error)),
+        };
+        let url = match url::Url::parse(&format!("http://{}", server.local_addr())) {
+            Ok(url) => url,
+            Err(error) => return Err(format!("Invalid mock server url: {:#?}", error)),
+        };
+        let (shutdown, receiver) = tokio::sync::oneshot::channel::<()>();
+        tokio::spawn(server.with_graceful_shutdown(async move {
+            let _ = receiver.await;
+        }));
+        Ok(MockServer {
+            url,
+            exchange,
+            shutdown: Some(shutdown),
+        })
+    }
+
+    pub fn url(&self) -> url::Url {
+        self.url.clone()
+    }
+
+    pub fn exchange(&self) -> &Arc<MockExchange> {
+        &self.exchange
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+    }
+}
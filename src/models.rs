@@ -1,49 +1,42 @@
-use crate::coin::Coins;
 use crate::base::Target;
 use crate::base::Side;
 
-pub type Currencies = Vec<CurrencyEntries>;
-pub type CurrencyEntries = (String, String, f64, Option<String>, f64);
+pub type Currencies = Vec<WalletEntry>;
 
-#[derive(Debug, Clone)]
+crate::positional_struct! {
+    /// One wallet of the balance response.
+    #[derive(Clone, Debug)]
+    pub struct WalletEntry {
+        0 => pub wallet_type: String,
+        1 => pub coin: crate::coin::Coin,
+        2 => pub full: f64,
+        4 => pub available: f64,
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
 pub struct Currency {
     pub coin: crate::coin::Coin,
     pub full: f64,
     pub available: f64,
 }
 
-impl Currency {
-    pub const COIN: usize = 1;
-    pub const FULL: usize = 2;
-    pub const AVAILABLE: usize = 4;
-
-    pub fn from(currency: &CurrencyEntries) -> Option<Self> {
-        let available = currency.4;
-        let full = currency.2;
-        Some(Currency {
-            coin: crate::coin::Coin::from(currency.1.as_ref()),
-            full,
-            available,
-        })
-    }
-}
-
-impl From<&Currency> for CurrencyEntries {
-    fn from(currency: &Currency) -> CurrencyEntries {
-        (
-            "exchange".to_owned(),
-            currency.coin.to_string(),
-            currency.full,
-            None,
-            currency.available,
-        )
+impl From<WalletEntry> for Currency {
+    fn from(wallet: WalletEntry) -> Currency {
+        Currency {
+            coin: wallet.coin,
+            full: wallet.full,
+            available: wallet.available,
+        }
     }
 }
 
 pub type OrderBookEntry = (f64, f64, u32);
 pub type OrderBookEntries = Vec<OrderBookEntry>;
 
-#[derive(Clone, Debug)]
+/// Serialized with `coins` as the market symbol and levels as `{price, amount, orders_number}`,
+/// amounts positive on both sides.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
 pub struct OrderBook {
     pub coins: crate::coin::Coins,
     pub bids: Vec<OrderBookItem>,
@@ -67,7 +60,7 @@ impl OrderBook {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
 pub struct OrderBookItem {
     pub price: f64,
     pub amount: f64,
@@ -84,7 +77,7 @@ impl From<OrderBookEntry> for OrderBookItem {
     }
 }
 
-#[derive(serde::Serialize, Clone, Debug)]
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
 pub struct CreateOrder {
     pub symbol: String,
     pub amount: f64,
@@ -95,155 +88,71 @@ pub struct CreateOrder {
 
 pub type CreateOrderResponseRaw = Vec<serde_json::Value>;
 
-#[derive(Clone, Debug)]
-pub struct CreateOrderResponse {
-    pub id: i32,
-    pub coins: crate::coin::Coins,
-    pub side: crate::base::Side,
-    pub target: crate::base::Target,
-    pub initial_amount: f64,
-    pub executed_amount: f64,
-    pub price: Option<f64>,
-}
-
-#[derive(Clone, Debug)]
-pub struct MyOrderResponse {
-    pub id: i32,
-    pub coins: crate::coin::Coins,
-    pub side: crate::base::Side,
-    pub target: crate::base::Target,
-    pub initial_amount: f64,
-    pub executed_amount: f64,
-    pub price: Option<f64>,
-}
+pub type CreateOrderResponse = crate::order::Order;
+pub type MyOrderResponse = crate::order::Order;
 
-fn json_to_id(value: Option<&serde_json::Value>) -> Result<i32, CreateOrderError> {
-    let value = match value {
-        Some(value) => value,
-        None => return Err(CreateOrderError::NoJson("id")),
-    };
-    match value {
-        serde_json::Value::Number(number) => match number.as_i64() {
-            Some(id) => Ok(id as i32),
-            None => Err(CreateOrderError::InvalidJson(value.clone())),
-        },
-        any => Err(CreateOrderError::InvalidJson(any.clone())),
-    }
-}
-
-fn json_to_amount(value: Option<&serde_json::Value>) -> Result<Option<f64>, CreateOrderError> {
+pub(crate) fn str_to_amount(value: &str) -> Result<f64, ParseError> {
     use std::str::FromStr;
-    let value = match value {
-        Some(value) => value,
-        None => return Err(CreateOrderError::NoJson("Amount")),
-    };
-    match value {
-        serde_json::Value::String(as_string) => match f64::from_str(&as_string) {
-            Ok(amount) => Ok(Some(amount)),
-            Err(error) => Err(CreateOrderError::InvalidAmount(error)),
-        },
-        serde_json::Value::Number(number) => match number.as_f64() {
-            Some(amount) => Ok(Some(amount)),
-            None => Err(CreateOrderError::InvalidJson(value.clone())),
-        },
-        serde_json::Value::Null => Ok(None),
-        any => Err(CreateOrderError::InvalidJson(any.clone())),
-    }
-}
-
-fn json_to_coins(
-    value: Option<&serde_json::Value>,
-) -> Result<Coins, CreateOrderError> {
-    use std::convert::TryFrom;
-    let value = match value {
-        Some(value) => value,
-        None => return Err(CreateOrderError::NoJson("Coins")),
-    };
-    match value {
-        serde_json::Value::String(string) => match Coins::try_from(string.as_ref()) {
-            Ok(coins) => Ok(coins),
-            Err(error) => Err(CreateOrderError::ErrorJson(error)),
-        },
-        any => Err(CreateOrderError::InvalidJson(any.clone())),
+    match f64::from_str(value) {
+        Ok(amount) => Ok(amount),
+        Err(error) => Err(ParseError::InvalidAmount(error)),
     }
 }
 
-fn json_to_target(
-    value: Option<&serde_json::Value>,
-) -> Result<Target, CreateOrderError> {
-    use std::convert::TryFrom;
-    let value = match value {
-        Some(value) => value,
-        None => return Err(CreateOrderError::NoJson("Target")),
-    };
-    match value {
-        serde_json::Value::String(string) => match Target::try_from(string.to_lowercase().as_ref()) {
-            Ok(target) => Ok(target),
-            Err(error) => Err(CreateOrderError::ErrorJson(error)),
-        },
-        any => Err(CreateOrderError::InvalidJson(any.clone())),
-    }
-}
-
-impl std::convert::TryFrom<CreateOrderResponseRaw> for CreateOrderResponse {
-    type Error = CreateOrderError;
-
-    fn try_from(response: CreateOrderResponseRaw) -> Result<Self, Self::Error> {
-        if response.len() < 17 {
-            return Err(CreateOrderError::InvalidResponseLength);
-        };
-        let executed_amount = json_to_amount(response.get(7))?
-            .expect("Executed amount is null");
-        let initial_amount = json_to_amount(response.get(6))?
-            .expect("Initial amount is null");
-        let price = json_to_amount(response.get(16))?;
-        Ok(CreateOrderResponse {
-            id: json_to_id(response.get(0))?,
-            coins: json_to_coins(response.get(3))?,
-            side: if initial_amount < 0.0 { Side::Sell } else { Side::Buy },
-            target: json_to_target(response.get(8))?,
-            initial_amount,
-            executed_amount,
-            price,
-        })
-    }
-}
-
-impl std::convert::TryFrom<CreateOrderResponseRaw> for MyOrderResponse {
-    type Error = CreateOrderError;
-
-    fn try_from(response: CreateOrderResponseRaw) -> Result<Self, Self::Error> {
-        if response.len() < 17 {
-            return Err(CreateOrderError::InvalidResponseLength);
-        };
-        let executed_amount = json_to_amount(response.get(6))?
-            .expect("Executed amount is null");
-        let initial_amount = json_to_amount(response.get(7))?
-            .expect("Initial amount is null");
-        let price = json_to_amount(response.get(16))?;
-        Ok(MyOrderResponse {
-            id: json_to_id(response.get(0))?,
-            coins: json_to_coins(response.get(3))?,
-            side: if initial_amount < 0.0 { Side::Sell } else { Side::Buy },
-            target: json_to_target(response.get(8))?,
-            initial_amount,
-            executed_amount,
-            price,
-        })
-    }
-}
+pub type CreateOrderError = ParseError;
 
+/// Error of every conversion from a raw response into a model.
 #[derive(Clone, Debug)]
-pub enum CreateOrderError {
+pub enum ParseError {
     InvalidCoins(&'static str),
     InvalidAmount(<f64 as std::str::FromStr>::Err),
     InvalidTarget(&'static str),
+    InvalidSide(&'static str),
     InvalidJson(serde_json::Value),
     ErrorJson(&'static str),
     NoJson(&'static str),
     InvalidResponseLength,
+    // A required position is past the end of an array response.
+    MissingField {
+        index: usize,
+        field: &'static str,
+        expected: &'static str,
+    },
+    InvalidField {
+        index: usize,
+        field: &'static str,
+        expected: &'static str,
+        found: serde_json::Value,
+    },
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseError::InvalidCoins(error) => write!(formatter, "Invalid coins: {}", error),
+            ParseError::InvalidAmount(error) => write!(formatter, "Invalid amount: {}", error),
+            ParseError::InvalidTarget(error) => write!(formatter, "Invalid target: {}", error),
+            ParseError::InvalidSide(error) => write!(formatter, "Invalid side: {}", error),
+            ParseError::InvalidJson(value) => write!(formatter, "Unexpected json: {}", value),
+            ParseError::ErrorJson(error) => write!(formatter, "Json error: {}", error),
+            ParseError::NoJson(field) => write!(formatter, "Missing field: {}", field),
+            ParseError::InvalidResponseLength => write!(formatter, "Invalid response length"),
+            ParseError::MissingField { index, field, expected } => write!(
+                formatter,
+                "Missing field `{}` at index {}, expected {}",
+                field, index, expected
+            ),
+            ParseError::InvalidField { index, field, expected, found } => write!(
+                formatter,
+                "Invalid field `{}` at index {}, expected {}, found {}",
+                field, index, expected, found
+            ),
+        }
+    }
 }
 
+impl std::error::Error for ParseError {}
+
 impl CreateOrder {
     pub fn new(
         coins: crate::coin::Coins,
@@ -265,11 +174,27 @@ impl CreateOrder {
             },
         }
     }
+
+    pub fn matches(&self, order: &crate::order::Order) -> bool {
+        const EPSILON: f64 = 1e-9;
+        if order.coins.to_string() != self.symbol
+            || order.target.to_string() != self.order_type
+            || (order.signed_original_amount() - self.amount).abs() > EPSILON
+        {
+            return false;
+        }
+        match order.target {
+            Target::Limit => order
+                .price
+                .is_some_and(|price| (price - self.price).abs() <= EPSILON),
+            Target::Market => true,
+        }
+    }
 }
 
 pub type Markets = Vec<Market>;
 
-#[derive(serde::Deserialize, Clone, Debug)]
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
 pub struct Market {
     pub id: String,
     pub base_unit: String,
@@ -280,7 +205,17 @@ pub struct Market {
     pub price_change: f64,
 }
 
-#[derive(serde::Serialize, Clone, Debug)]
+impl Market {
+    pub fn price_tick(&self) -> f64 {
+        10f64.powi(-self.quote_precision)
+    }
+
+    pub fn amount_step(&self) -> f64 {
+        10f64.powi(-self.base_precision)
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
 pub struct CancelOrderRequest {
     pub order_id: i32,
 }
@@ -2,34 +2,376 @@ use crate::base;
 use crate::coin;
 use crate::models;
 use std::convert::TryFrom;
-use std::str::FromStr;
 
-#[derive(Debug, Clone)]
-pub struct CanceledOrder {
-    pub symbol: coin::Coins,
+pub type CanceledOrder = Order;
+
+crate::positional_struct! {
+    /// Order array of submission, open orders and order history responses.
+    /// Amounts are negative for sells.
+    #[derive(Clone, Debug)]
+    pub struct OrderEntry {
+        0 => pub id: i32,
+        3 => pub coins: coin::Coins,
+        4 => pub created_at: Option<chrono::DateTime<chrono::Utc>>,
+        5 => pub updated_at: Option<chrono::DateTime<chrono::Utc>>,
+        6 => pub remaining_amount: f64,
+        7 => pub original_amount: f64,
+        8 => pub target: base::Target,
+        13 => pub status: Option<String>,
+        16 => pub price: Option<f64>,
+        17 => pub average_price: Option<f64>,
+    }
+}
+
+/// Order as returned by order submission, open orders, order history and cancellation.
+/// Amounts are kept positive, the direction is carried by `side`.
+/// Serialized with timestamps as RFC 3339 strings and `status` as described on `OrderStatus`.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+pub struct Order {
+    pub id: i32,
+    pub coins: coin::Coins,
     pub side: base::Side,
     pub target: base::Target,
-    pub price: f64,
+    pub status: OrderStatus,
+    pub created_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub updated_at: Option<chrono::DateTime<chrono::Utc>>,
     pub original_amount: f64,
     pub remaining_amount: f64,
+    pub executed_amount: f64,
+    pub price: Option<f64>,
+    pub average_price: Option<f64>,
+}
+
+impl Order {
+    pub fn signed_original_amount(&self) -> f64 {
+        match self.side {
+            base::Side::Buy => self.original_amount,
+            base::Side::Sell => -self.original_amount,
+        }
+    }
 }
 
-impl TryFrom<models::CanceledOrderResponse> for CanceledOrder {
-    type Error = &'static str;
+impl From<OrderEntry> for Order {
+    fn from(entry: OrderEntry) -> Order {
+        let original_amount = entry.original_amount.abs();
+        let remaining_amount = entry.remaining_amount.abs();
+        let executed_amount = original_amount - remaining_amount;
+        Order {
+            id: entry.id,
+            coins: entry.coins,
+            side: if entry.original_amount < 0.0 { base::Side::Sell } else { base::Side::Buy },
+            target: entry.target,
+            status: match entry.status {
+                Some(status) => OrderStatus::from_state(&status, executed_amount),
+                None => OrderStatus::from_amounts(original_amount, remaining_amount),
+            },
+            created_at: entry.created_at,
+            updated_at: entry.updated_at,
+            original_amount,
+            remaining_amount,
+            executed_amount,
+            price: entry.price,
+            average_price: entry.average_price,
+        }
+    }
+}
+
+impl TryFrom<models::CreateOrderResponseRaw> for Order {
+    type Error = models::ParseError;
+
+    fn try_from(response: models::CreateOrderResponseRaw) -> Result<Self, Self::Error> {
+        OrderEntry::from_values(&response).map(Order::from)
+    }
+}
 
-    fn try_from(order: models::CanceledOrderResponse) -> Result<CanceledOrder, Self::Error> {
-        Ok(CanceledOrder {
-            symbol: coin::Coins::try_from(order.symbol.as_ref())?,
-            side: base::Side::try_from(order.side.as_ref())?,
-            target: base::Target::try_from(order.order_type.as_ref())?,
-            price: f64::from_str(&order.price).expect("Invalid Number"),
-            original_amount: f64::from_str(&order.original_amount).expect("Invalid number"),
-            remaining_amount: f64::from_str(&order.remaining_amount).expect("Invalid number"),
+impl TryFrom<models::CanceledOrderResponse> for Order {
+    type Error = models::ParseError;
+
+    fn try_from(order: models::CanceledOrderResponse) -> Result<Order, Self::Error> {
+        use chrono::TimeZone;
+        let executed_amount = models::str_to_amount(&order.executed_amount)?;
+        Ok(Order {
+            id: order.id,
+            coins: coin::Coins::try_from(order.symbol.as_ref()).map_err(models::ParseError::InvalidCoins)?,
+            side: base::Side::try_from(order.side.as_ref()).map_err(models::ParseError::InvalidSide)?,
+            target: base::Target::try_from(order.order_type.as_ref()).map_err(models::ParseError::InvalidTarget)?,
+            status: OrderStatus::from_state(&order.state, executed_amount),
+            created_at: None,
+            updated_at: if order.timestamp < 100_000_000_000 {
+                chrono::Utc.timestamp_opt(order.timestamp as i64, 0).single()
+            } else {
+                chrono::Utc.timestamp_millis_opt(order.timestamp as i64).single()
+            },
+            original_amount: models::str_to_amount(&order.original_amount)?,
+            remaining_amount: models::str_to_amount(&order.remaining_amount)?,
+            executed_amount,
+            price: Some(models::str_to_amount(&order.price)?),
+            average_price: models::str_to_amount(&order.avg_execution_price).ok(),
         })
     }
 }
 
-#[derive(Clone, Debug)]
+/// Serialized in snake case, e.g. "partially_filled", `Unknown` as `{"unknown": status}`.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
 pub enum OrderStatus {
+    // Accepted and waiting in the book, nothing executed yet.
+    Active,
+    PartiallyFilled,
     Executed,
+    Canceled,
+    // Canceled after part of the amount had been executed.
+    PartiallyCanceled,
+    Rejected,
+    Unknown(String),
+}
+
+impl OrderStatus {
+    /// Parses a status string, either the `state` of a canceled order ("canceled")
+    /// or the status of an order array ("PARTIALLY FILLED @ 100.0(1.0)").
+    /// `executed_amount` tells a partially filled cancellation from a plain one.
+    pub fn from_state(state: &str, executed_amount: f64) -> OrderStatus {
+        let normalized = state.trim().to_uppercase();
+        if normalized.starts_with("CANCELED") || normalized.starts_with("CANCELLED") {
+            if executed_amount > 0.0 || normalized.contains("PARTIALLY FILLED") {
+                OrderStatus::PartiallyCanceled
+            } else {
+                OrderStatus::Canceled
+            }
+        } else if normalized.starts_with("PARTIALLY FILLED") {
+            OrderStatus::PartiallyFilled
+        } else if normalized.starts_with("EXECUTED") || normalized.starts_with("FILLED") {
+            OrderStatus::Executed
+        } else if normalized.starts_with("ACTIVE") || normalized.starts_with("NEW") {
+            OrderStatus::Active
+        } else if normalized.starts_with("REJECTED") || normalized.starts_with("INSUFFICIENT") {
+            OrderStatus::Rejected
+        } else {
+            OrderStatus::Unknown(state.to_owned())
+        }
+    }
+
+    /// Status implied by the amounts alone, for responses without a status field.
+    pub fn from_amounts(original_amount: f64, remaining_amount: f64) -> OrderStatus {
+        if remaining_amount <= 0.0 {
+            OrderStatus::Executed
+        } else if remaining_amount < original_amount {
+            OrderStatus::PartiallyFilled
+        } else {
+            OrderStatus::Active
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        matches!(self, OrderStatus::Active | OrderStatus::PartiallyFilled)
+    }
+
+    pub fn is_terminal(&self) -> bool {
+        matches!(
+            self,
+            OrderStatus::Executed
+                | OrderStatus::Canceled
+                | OrderStatus::PartiallyCanceled
+                | OrderStatus::Rejected
+        )
+    }
+
+    pub fn is_canceled(&self) -> bool {
+        matches!(self, OrderStatus::Canceled | OrderStatus::PartiallyCanceled)
+    }
+}
+
+impl std::fmt::Display for OrderStatus {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OrderStatus::Active => write!(formatter, "ACTIVE"),
+            OrderStatus::PartiallyFilled => write!(formatter, "PARTIALLY FILLED"),
+            OrderStatus::Executed => write!(formatter, "EXECUTED"),
+            OrderStatus::Canceled => write!(formatter, "CANCELED"),
+            OrderStatus::PartiallyCanceled => write!(formatter, "CANCELED was: PARTIALLY FILLED"),
+            OrderStatus::Rejected => write!(formatter, "REJECTED"),
+            OrderStatus::Unknown(status) => write!(formatter, "{}", status),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    // Captured responses, amounts negative for sells and the remaining amount before the original one.
+    const CREATED: &str = r#"[100234521, null, null, "tonusdt", 1700000000000, 1700000000000, "1.5", "1.5",
+        "LIMIT", null, null, null, null, "ACTIVE", null, null, "2.5", "0"]"#;
+    const OPEN_ORDERS: &str = r#"[
+        [100234521, null, null, "tonusdt", 1700000000000, 1700000000000, "1.5", "1.5",
+            "LIMIT", null, null, null, null, "ACTIVE", null, null, "2.5", "0"],
+        [100234522, null, null, "tonusdt", 1700000001000, 1700000002500, "-0.25", "-1",
+            "LIMIT", null, null, null, null, "PARTIALLY FILLED @ 2.6(0.75)", null, null, "2.6", "2.6"]
+    ]"#;
+    const ORDER_HISTORY: &str = r#"[
+        [100234500, null, null, "tonusdt", 1699999000000, 1699999000450, 0, 2,
+            "MARKET", null, null, null, null, "EXECUTED @ 2.51(2.0)", null, null, null, 2.51],
+        [100234501, null, null, "tonusdt", 1699999500000, 1699999600000, "-3", "-4",
+            "LIMIT", null, null, null, null, "CANCELED was: PARTIALLY FILLED @ 2.7(1.0)", null, null, "2.7", "2.7"]
+    ]"#;
+    const CANCELED: &str = r#"{
+        "id": 100234522,
+        "side": "sell",
+        "type": "limit",
+        "price": "2.6",
+        "avg_execution_price": "2.6",
+        "state": "canceled",
+        "symbol": "tonusdt",
+        "timestamp": 1700000003000,
+        "original_amount": "1",
+        "remaining_amount": "0.25",
+        "executed_amount": "0.75",
+        "is_cancelled": "true",
+        "is_hidden": "false",
+        "is_live": "false",
+        "was_forced": "false",
+        "exchange": "kuna"
+    }"#;
+
+    fn time(milliseconds: i64) -> Option<chrono::DateTime<chrono::Utc>> {
+        chrono::Utc.timestamp_millis_opt(milliseconds).single()
+    }
+
+    fn orders(payload: &str) -> Vec<Order> {
+        serde_json::from_str::<Vec<models::CreateOrderResponseRaw>>(payload)
+            .expect("Failed to parse payload")
+            .into_iter()
+            .map(|order| Order::try_from(order).expect("Failed to convert order"))
+            .collect()
+    }
+
+    #[test]
+    fn created_order() {
+        let response: models::CreateOrderResponseRaw = serde_json::from_str(CREATED).expect("Failed to parse payload");
+        let order = Order::try_from(response).expect("Failed to convert order");
+        assert_eq!(order.id, 100234521);
+        assert_eq!(order.coins, coin::Coins::TonUsdt);
+        assert_eq!(order.side, base::Side::Buy);
+        assert_eq!(order.target, base::Target::Limit);
+        assert_eq!(order.status, OrderStatus::Active);
+        assert_eq!(order.created_at, time(1700000000000));
+        assert_eq!(order.updated_at, time(1700000000000));
+        assert_eq!(order.original_amount, 1.5);
+        assert_eq!(order.remaining_amount, 1.5);
+        assert_eq!(order.executed_amount, 0.0);
+        assert_eq!(order.price, Some(2.5));
+        assert_eq!(order.average_price, Some(0.0));
+    }
+
+    #[test]
+    fn open_orders() {
+        let orders = orders(OPEN_ORDERS);
+        assert_eq!(orders.len(), 2);
+        assert_eq!(orders[0].id, 100234521);
+        assert_eq!(orders[0].status, OrderStatus::Active);
+
+        let order = &orders[1];
+        assert_eq!(order.id, 100234522);
+        assert_eq!(order.coins, coin::Coins::TonUsdt);
+        assert_eq!(order.side, base::Side::Sell);
+        assert_eq!(order.target, base::Target::Limit);
+        assert_eq!(order.status, OrderStatus::PartiallyFilled);
+        assert_eq!(order.created_at, time(1700000001000));
+        assert_eq!(order.updated_at, time(1700000002500));
+        assert_eq!(order.original_amount, 1.0);
+        assert_eq!(order.remaining_amount, 0.25);
+        assert_eq!(order.executed_amount, 0.75);
+        assert_eq!(order.signed_original_amount(), -1.0);
+        assert_eq!(order.price, Some(2.6));
+        assert_eq!(order.average_price, Some(2.6));
+    }
+
+    #[test]
+    fn order_history() {
+        let orders = orders(ORDER_HISTORY);
+        assert_eq!(orders.len(), 2);
+
+        let executed = &orders[0];
+        assert_eq!(executed.id, 100234500);
+        assert_eq!(executed.coins, coin::Coins::TonUsdt);
+        assert_eq!(executed.side, base::Side::Buy);
+        assert_eq!(executed.target, base::Target::Market);
+        assert_eq!(executed.status, OrderStatus::Executed);
+        assert_eq!(executed.created_at, time(1699999000000));
+        assert_eq!(executed.updated_at, time(1699999000450));
+        assert_eq!(executed.original_amount, 2.0);
+        assert_eq!(executed.remaining_amount, 0.0);
+        assert_eq!(executed.executed_amount, 2.0);
+        assert_eq!(executed.price, None);
+        assert_eq!(executed.average_price, Some(2.51));
+
+        let canceled = &orders[1];
+        assert_eq!(canceled.id, 100234501);
+        assert_eq!(canceled.coins, coin::Coins::TonUsdt);
+        assert_eq!(canceled.side, base::Side::Sell);
+        assert_eq!(canceled.target, base::Target::Limit);
+        assert_eq!(canceled.status, OrderStatus::PartiallyCanceled);
+        assert_eq!(canceled.created_at, time(1699999500000));
+        assert_eq!(canceled.updated_at, time(1699999600000));
+        assert_eq!(canceled.original_amount, 4.0);
+        assert_eq!(canceled.remaining_amount, 3.0);
+        assert_eq!(canceled.executed_amount, 1.0);
+        assert_eq!(canceled.price, Some(2.7));
+        assert_eq!(canceled.average_price, Some(2.7));
+    }
+
+    #[test]
+    fn canceled_order() {
+        let response: models::CanceledOrderResponse = serde_json::from_str(CANCELED).expect("Failed to parse payload");
+        let order = Order::try_from(response).expect("Failed to convert order");
+        assert_eq!(order.id, 100234522);
+        assert_eq!(order.coins, coin::Coins::TonUsdt);
+        assert_eq!(order.side, base::Side::Sell);
+        assert_eq!(order.target, base::Target::Limit);
+        assert_eq!(order.status, OrderStatus::PartiallyCanceled);
+        assert_eq!(order.created_at, None);
+        assert_eq!(order.updated_at, time(1700000003000));
+        assert_eq!(order.original_amount, 1.0);
+        assert_eq!(order.remaining_amount, 0.25);
+        assert_eq!(order.executed_amount, 0.75);
+        assert_eq!(order.price, Some(2.6));
+        assert_eq!(order.average_price, Some(2.6));
+    }
+
+    #[test]
+    fn every_status_is_parsed_and_classified() {
+        // State, executed amount, status, open, terminal, canceled.
+        let table = vec![
+            ("ACTIVE", 0.0, OrderStatus::Active, true, false, false),
+            ("new", 0.0, OrderStatus::Active, true, false, false),
+            ("PARTIALLY FILLED @ 2.6(0.75)", 0.75, OrderStatus::PartiallyFilled, true, false, false),
+            ("EXECUTED @ 2.51(2.0)", 2.0, OrderStatus::Executed, false, true, false),
+            ("filled", 2.0, OrderStatus::Executed, false, true, false),
+            ("canceled", 0.0, OrderStatus::Canceled, false, true, true),
+            ("CANCELLED", 0.0, OrderStatus::Canceled, false, true, true),
+            ("canceled", 0.75, OrderStatus::PartiallyCanceled, false, true, true),
+            ("CANCELED was: PARTIALLY FILLED @ 2.7(1.0)", 0.0, OrderStatus::PartiallyCanceled, false, true, true),
+            ("REJECTED", 0.0, OrderStatus::Rejected, false, true, false),
+            ("INSUFFICIENT BALANCE", 0.0, OrderStatus::Rejected, false, true, false),
+            ("PENDING", 0.0, OrderStatus::Unknown("PENDING".to_owned()), false, false, false),
+        ];
+        for (state, executed_amount, status, open, terminal, canceled) in table {
+            let parsed = OrderStatus::from_state(state, executed_amount);
+            assert_eq!(parsed, status, "{}", state);
+            assert_eq!(
+                (parsed.is_open(), parsed.is_terminal(), parsed.is_canceled()),
+                (open, terminal, canceled),
+                "{}",
+                state
+            );
+        }
+    }
+
+    #[test]
+    fn status_is_implied_by_amounts() {
+        assert_eq!(OrderStatus::from_amounts(2.0, 2.0), OrderStatus::Active);
+        assert_eq!(OrderStatus::from_amounts(2.0, 0.5), OrderStatus::PartiallyFilled);
+        assert_eq!(OrderStatus::from_amounts(2.0, 0.0), OrderStatus::Executed);
+    }
 }
@@ -0,0 +1,444 @@
+use crate::base::Side;
+use crate::base::Target;
+use crate::coin::Coin;
+use crate::coin::Coins;
+use crate::models;
+use crate::order::OrderStatus;
+use crate::public::KunaPublicClient;
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::sync::Mutex;
+
+const EPSILON: f64 = 1e-12;
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+struct PaperOrder {
+    id: i32,
+    symbol: Coins,
+    side: Side,
+    order_type: Target,
+    // Limit price, `None` for market orders.
+    price: Option<f64>,
+    original_amount: f64,
+    remaining_amount: f64,
+    notional: f64,
+    canceled: bool,
+    #[serde(default)]
+    created_at: i64,
+    #[serde(default)]
+    updated_at: i64,
+}
+
+impl PaperOrder {
+    fn is_open(&self) -> bool {
+        !self.canceled && self.remaining_amount > EPSILON
+    }
+
+    fn executed_amount(&self) -> f64 {
+        self.original_amount - self.remaining_amount
+    }
+
+    fn status(&self) -> OrderStatus {
+        if self.canceled && self.executed_amount() > EPSILON {
+            OrderStatus::PartiallyCanceled
+        } else if self.canceled {
+            OrderStatus::Canceled
+        } else if self.remaining_amount <= EPSILON {
+            OrderStatus::Executed
+        } else if self.executed_amount() > EPSILON {
+            OrderStatus::PartiallyFilled
+        } else {
+            OrderStatus::Active
+        }
+    }
+
+    fn to_order(&self) -> crate::order::Order {
+        use chrono::TimeZone;
+        let executed_amount = self.executed_amount();
+        crate::order::Order {
+            id: self.id,
+            coins: self.symbol.clone(),
+            side: self.side,
+            target: self.order_type,
+            status: self.status(),
+            created_at: chrono::Utc.timestamp_millis_opt(self.created_at).single(),
+            updated_at: chrono::Utc.timestamp_millis_opt(self.updated_at).single(),
+            original_amount: self.original_amount,
+            remaining_amount: self.remaining_amount,
+            executed_amount,
+            // Files saved by earlier versions keep market orders with a price of 0.
+            price: self.price.filter(|price| *price > 0.0),
+            average_price: if executed_amount > EPSILON {
+                Some(self.notional / executed_amount)
+            } else {
+                None
+            },
+        }
+    }
+}
+
+// Liquidity that simulated fills took from a level of the live book, which the exchange
+// does not know about. `side` is the side of the orders that took it.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+struct ConsumedLevel {
+    side: Side,
+    price: f64,
+    amount: f64,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, Default)]
+struct PaperState {
+    balances: BTreeMap<String, f64>,
+    orders: Vec<PaperOrder>,
+    next_id: i32,
+    // Keyed by market symbol.
+    #[serde(default)]
+    consumed: BTreeMap<String, Vec<ConsumedLevel>>,
+}
+
+impl PaperState {
+    fn balance(&self, coin: &Coin) -> f64 {
+        self.balances.get(&coin.to_string()).copied().unwrap_or(0.0)
+    }
+
+    // Open buys reserve their notional and the fee charged when they fill.
+    fn reserved(&self, coin: &Coin, fee_rate: f64) -> f64 {
+        self.orders
+            .iter()
+            .filter(|order| order.is_open())
+            .map(|order| match order.side {
+                Side::Buy if &order.symbol.quote_coin() == coin => {
+                    order.remaining_amount * order.price.unwrap_or(0.0) * (1.0 + fee_rate)
+                },
+                Side::Sell if &order.symbol.base_coin() == coin => order.remaining_amount,
+                _ => 0.0,
+            })
+            .sum()
+    }
+
+    fn available(&self, coin: &Coin, fee_rate: f64) -> f64 {
+        self.balance(coin) - self.reserved(coin, fee_rate)
+    }
+
+    fn settle(&mut self, coins: &Coins, side: Side, amount: f64, notional: f64, fee_rate: f64) {
+        let (base_delta, quote_delta) = match side {
+            Side::Buy => (amount, -notional * (1.0 + fee_rate)),
+            Side::Sell => (-amount, notional * (1.0 - fee_rate)),
+        };
+        *self.balances.entry(coins.base_coin().to_string()).or_insert(0.0) += base_delta;
+        *self.balances.entry(coins.quote_coin().to_string()).or_insert(0.0) += quote_delta;
+    }
+
+    /// The live `book` without the liquidity earlier fills took from it. A level that shrank
+    /// below what was taken, or disappeared, is assumed to reflect those fills already.
+    fn remaining_book(&mut self, book: &models::OrderBook) -> models::OrderBook {
+        let consumed = self.consumed.entry(book.coins.to_string()).or_default();
+        let mut remaining = book.clone();
+        consumed.retain_mut(|taken| {
+            let level = book_side(&mut remaining, taken.side)
+                .iter_mut()
+                .find(|level| level.price == taken.price);
+            match level {
+                Some(level) => {
+                    taken.amount = taken.amount.min(level.amount);
+                    level.amount -= taken.amount;
+                    taken.amount > EPSILON
+                },
+                None => false,
+            }
+        });
+        remaining.asks.retain(|level| level.amount > EPSILON);
+        remaining.bids.retain(|level| level.amount > EPSILON);
+        remaining
+    }
+
+    fn consume(&mut self, coins: &Coins, side: Side, taken: &[(f64, f64)]) {
+        let consumed = self.consumed.entry(coins.to_string()).or_default();
+        for (price, amount) in taken.iter().copied() {
+            match consumed
+                .iter_mut()
+                .find(|level| level.side == side && level.price == price)
+            {
+                Some(level) => level.amount += amount,
+                None => consumed.push(ConsumedLevel { side, price, amount }),
+            }
+        }
+    }
+}
+
+fn book_side(book: &mut models::OrderBook, side: Side) -> &mut Vec<models::OrderBookItem> {
+    match side {
+        Side::Buy => &mut book.asks,
+        Side::Sell => &mut book.bids,
+    }
+}
+
+// Walks the levels an order on `side` executes against, stopping at `limit` if given, and
+// removes what it takes from `book`. Returns the filled amount, its notional and the
+// amount taken at each price.
+fn take_liquidity(
+    book: &mut models::OrderBook,
+    side: Side,
+    amount: f64,
+    limit: Option<f64>,
+) -> (f64, f64, Vec<(f64, f64)>) {
+    let taken: Vec<(f64, f64)> = book
+        .levels(side)
+        .into_iter()
+        .take_while(|level| match (side, limit) {
+            (_, None) => true,
+            (Side::Buy, Some(limit)) => level.price <= limit,
+            (Side::Sell, Some(limit)) => level.price >= limit,
+        })
+        .scan(0.0, |filled, level| {
+            let take = (amount - *filled).min(level.amount).max(0.0);
+            *filled += take;
+            Some((level.price, take))
+        })
+        .filter(|(_, take)| *take > EPSILON)
+        .collect();
+    for (price, take) in taken.iter().copied() {
+        if let Some(level) = book_side(book, side).iter_mut().find(|level| level.price == price) {
+            level.amount -= take;
+        }
+    }
+    let filled = taken.iter().map(|(_, take)| take).sum();
+    let notional = taken.iter().map(|(price, take)| price * take).sum();
+    (filled, notional, taken)
+}
+
+/// Simulates the private part of `KunaClient` against live order books from `KunaPublicClient`.
+/// Fills are taken from the book at the time of each call and charged `fee_rate` in the quote coin.
+/// Liquidity taken by simulated fills stays taken until the live book shows the level shrinking
+/// or gone, so orders never fill twice against the same resting amount.
+pub struct PaperClient {
+    public_client: KunaPublicClient,
+    fee_rate: f64,
+    state: Mutex<PaperState>,
+    path: Option<std::path::PathBuf>,
+}
+
+impl PaperClient {
+    pub fn new(public_client: KunaPublicClient) -> PaperClient {
+        PaperClient {
+            public_client,
+            fee_rate: 0.0,
+            state: Mutex::new(PaperState::default()),
+            path: None,
+        }
+    }
+
+    pub fn with_fee_rate(mut self, fee_rate: f64) -> PaperClient {
+        self.fee_rate = fee_rate;
+        self
+    }
+
+    pub fn with_balance(self, coin: Coin, amount: f64) -> PaperClient {
+        self.state().balances.insert(coin.to_string(), amount);
+        self
+    }
+
+    /// Keeps virtual balances and orders in the JSON file at `path`, loading it if it exists.
+    pub fn persisted(mut self, path: std::path::PathBuf) -> Result<PaperClient, String> {
+        if path.exists() {
+            let json = match std::fs::read(&path) {
+                Ok(json) => json,
+                Err(error) => return Err(format!("Failed to read {:?}: {:#?}", path, error)),
+            };
+            let state = match serde_json::from_slice::<PaperState>(&json) {
+                Ok(state) => state,
+                Err(error) => return Err(format!("Invalid paper state {:?}: {:#?}", path, error)),
+            };
+            *self.state() = state;
+        }
+        self.path = Some(path);
+        Ok(self)
+    }
+
+    pub fn public_client(&self) -> &KunaPublicClient {
+        &self.public_client
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, PaperState> {
+        self.state.lock().expect("Paper state lock is poisoned")
+    }
+
+    fn save(&self, state: &PaperState) -> Result<(), String> {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        let json = match serde_json::to_string_pretty(state) {
+            Ok(json) => json,
+            Err(error) => return Err(format!("Failed to serialize paper state: {:#?}", error)),
+        };
+        match std::fs::write(path, json) {
+            Ok(()) => Ok(()),
+            Err(error) => Err(format!("Failed to write {:?}: {:#?}", path, error)),
+        }
+    }
+
+    // Fills resting limit orders of `coins` that the current book crosses.
+    async fn refresh(&self, coins: &Coins) -> Result<(), String> {
+        let has_open_orders = self
+            .state()
+            .orders
+            .iter()
+            .any(|order| order.is_open() && &order.symbol == coins);
+        if !has_open_orders {
+            return Ok(());
+        }
+        let book = self.public_client.get_orderbook(coins.clone()).await?;
+        let mut state = self.state();
+        let mut book = state.remaining_book(&book);
+        let mut fills = Vec::new();
+        // Older orders take the liquidity first.
+        for order in state.orders.iter_mut().filter(|order| {
+            order.is_open() && &order.symbol == coins
+        }) {
+            let side = order.side;
+            let (filled, notional, taken) = take_liquidity(&mut book, side, order.remaining_amount, order.price);
+            if filled > EPSILON {
+                order.remaining_amount -= filled;
+                order.notional += notional;
+                order.updated_at = chrono::Utc::now().timestamp_millis();
+                fills.push((side, filled, notional, taken));
+            }
+        }
+        for (side, filled, notional, taken) in fills {
+            state.settle(coins, side, filled, notional, self.fee_rate);
+            state.consume(coins, side, &taken);
+        }
+        self.save(&state)
+    }
+
+    pub async fn get_balance(&self) -> Result<Vec<models::Currency>, String> {
+        let symbols: std::collections::HashSet<Coins> = self
+            .state()
+            .orders
+            .iter()
+            .filter(|order| order.is_open())
+            .map(|order| order.symbol.clone())
+            .collect();
+        for coins in symbols {
+            self.refresh(&coins).await?;
+        }
+        let state = self.state();
+        Ok(state
+            .balances
+            .keys()
+            .map(|coin| {
+                let coin = Coin::from(coin.as_ref());
+                models::Currency {
+                    full: state.balance(&coin),
+                    available: state.available(&coin, self.fee_rate),
+                    coin,
+                }
+            })
+            .collect())
+    }
+
+    pub async fn create_order(
+        &self,
+        order: models::CreateOrder,
+    ) -> Result<crate::order::Order, String> {
+        let coins = Coins::try_from(order.symbol.as_ref()).map_err(|error| error.to_owned())?;
+        let target = Target::try_from(order.order_type.as_ref()).map_err(|error| error.to_owned())?;
+        let side = if order.amount < 0.0 { Side::Sell } else { Side::Buy };
+        let amount = order.amount.abs();
+        if amount <= EPSILON {
+            return Err("Invalid amount".to_owned());
+        }
+        if target == Target::Limit && order.price <= 0.0 {
+            return Err("Invalid price".to_owned());
+        }
+        let book = self.public_client.get_orderbook(coins.clone()).await?;
+        let limit = match target {
+            Target::Limit => Some(order.price),
+            Target::Market => None,
+        };
+        let mut state = self.state();
+        let mut book = state.remaining_book(&book);
+        let (filled, notional, taken) = take_liquidity(&mut book, side, amount, limit);
+        let (coin, required) = match side {
+            Side::Buy => (
+                coins.quote_coin(),
+                (notional
+                    + match target {
+                        Target::Limit => (amount - filled) * order.price,
+                        Target::Market => 0.0,
+                    })
+                    * (1.0 + self.fee_rate),
+            ),
+            Side::Sell => (coins.base_coin(), amount),
+        };
+        if state.available(&coin, self.fee_rate) + EPSILON < required {
+            return Err(format!("Insufficient funds: {} required {}", coin, required));
+        }
+        if filled > EPSILON {
+            state.settle(&coins, side, filled, notional, self.fee_rate);
+            state.consume(&coins, side, &taken);
+        }
+        state.next_id += 1;
+        let timestamp = chrono::Utc::now().timestamp_millis();
+        let paper_order = PaperOrder {
+            id: state.next_id,
+            symbol: coins.clone(),
+            side,
+            order_type: target,
+            price: limit,
+            original_amount: amount,
+            remaining_amount: amount - filled,
+            notional,
+            // Market orders never rest in the book.
+            canceled: target == Target::Market && amount - filled > EPSILON,
+            created_at: timestamp,
+            updated_at: timestamp,
+        };
+        state.orders.push(paper_order.clone());
+        self.save(&state)?;
+        Ok(paper_order.to_order())
+    }
+
+    pub async fn delete_order(
+        &self,
+        cancel_order: models::CancelOrderRequest,
+    ) -> Result<crate::order::Order, String> {
+        let mut state = self.state();
+        let order = match state
+            .orders
+            .iter_mut()
+            .find(|order| order.id == cancel_order.order_id && order.is_open())
+        {
+            Some(order) => order,
+            None => return Err(format!("Order {} is not open", cancel_order.order_id)),
+        };
+        order.canceled = true;
+        order.updated_at = chrono::Utc::now().timestamp_millis();
+        let canceled = order.to_order();
+        self.save(&state)?;
+        Ok(canceled)
+    }
+
+    pub async fn get_my_orders(&self, coins: Coins) -> Result<Vec<crate::order::Order>, String> {
+        self.refresh(&coins).await?;
+        let state = self.state();
+        Ok(state
+            .orders
+            .iter()
+            .filter(|order| order.is_open() && order.symbol == coins)
+            .map(PaperOrder::to_order)
+            .collect())
+    }
+
+    pub async fn get_order_history(&self, coins: Coins) -> Result<Vec<crate::order::Order>, String> {
+        self.refresh(&coins).await?;
+        let state = self.state();
+        Ok(state
+            .orders
+            .iter()
+            .filter(|order| !order.is_open() && order.symbol == coins)
+            .map(PaperOrder::to_order)
+            .collect())
+    }
+}
@@ -0,0 +1,255 @@
+#[doc(hidden)]
+pub use serde;
+#[doc(hidden)]
+pub use serde_json;
+
+use crate::models::ParseError;
+
+/// A value that can be read from one position of a Kuna array response.
+pub trait PositionalField: Sized {
+    /// Describes the accepted json, used in error messages.
+    const EXPECTED: &'static str;
+
+    fn from_json(value: &serde_json::Value) -> Option<Self>;
+
+    /// Value of a position past the end of the array, `None` if the field is required.
+    fn from_missing() -> Option<Self> {
+        None
+    }
+}
+
+/// Reads the field `name` at `index` of `values`.
+pub fn field<T: PositionalField>(
+    values: &[serde_json::Value],
+    index: usize,
+    name: &'static str,
+) -> Result<T, ParseError> {
+    match values.get(index) {
+        Some(value) => match T::from_json(value) {
+            Some(field) => Ok(field),
+            None => Err(ParseError::InvalidField {
+                index,
+                field: name,
+                expected: T::EXPECTED,
+                found: value.clone(),
+            }),
+        },
+        None => match T::from_missing() {
+            Some(field) => Ok(field),
+            None => Err(ParseError::MissingField {
+                index,
+                field: name,
+                expected: T::EXPECTED,
+            }),
+        },
+    }
+}
+
+/// Declares a struct read from a json array, each field taken from the given index,
+/// e.g. `0 => pub id: i32`. Optional fields accept `null` and positions past the end of the array.
+#[macro_export]
+macro_rules! positional_struct {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident {
+            $(
+                $(#[$field_meta:meta])*
+                $index:literal => $field_vis:vis $field:ident : $type:ty
+            ),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        $vis struct $name {
+            $(
+                $(#[$field_meta])*
+                $field_vis $field: $type,
+            )*
+        }
+
+        impl $name {
+            pub fn from_values(
+                values: &[$crate::positional::serde_json::Value],
+            ) -> Result<$name, $crate::models::ParseError> {
+                Ok($name {
+                    $(
+                        $field: $crate::positional::field::<$type>(values, $index, stringify!($field))?,
+                    )*
+                })
+            }
+        }
+
+        impl<'de> $crate::positional::serde::Deserialize<'de> for $name {
+            fn deserialize<D>(deserializer: D) -> Result<$name, D::Error>
+            where
+                D: $crate::positional::serde::Deserializer<'de>,
+            {
+                let values: Vec<$crate::positional::serde_json::Value> =
+                    $crate::positional::serde::Deserialize::deserialize(deserializer)?;
+                $name::from_values(&values).map_err($crate::positional::serde::de::Error::custom)
+            }
+        }
+    };
+}
+
+impl<T: PositionalField> PositionalField for Option<T> {
+    const EXPECTED: &'static str = T::EXPECTED;
+
+    fn from_json(value: &serde_json::Value) -> Option<Option<T>> {
+        match value {
+            serde_json::Value::Null => Some(None),
+            value => T::from_json(value).map(Some),
+        }
+    }
+
+    fn from_missing() -> Option<Option<T>> {
+        Some(None)
+    }
+}
+
+impl PositionalField for serde_json::Value {
+    const EXPECTED: &'static str = "any json";
+
+    fn from_json(value: &serde_json::Value) -> Option<serde_json::Value> {
+        Some(value.clone())
+    }
+}
+
+impl PositionalField for f64 {
+    const EXPECTED: &'static str = "number or numeric string";
+
+    fn from_json(value: &serde_json::Value) -> Option<f64> {
+        use std::str::FromStr;
+        match value {
+            serde_json::Value::Number(number) => number.as_f64(),
+            serde_json::Value::String(string) => f64::from_str(string).ok(),
+            _ => None,
+        }
+    }
+}
+
+impl PositionalField for i64 {
+    const EXPECTED: &'static str = "integer";
+
+    fn from_json(value: &serde_json::Value) -> Option<i64> {
+        value.as_i64()
+    }
+}
+
+impl PositionalField for i32 {
+    const EXPECTED: &'static str = "32 bit integer";
+
+    fn from_json(value: &serde_json::Value) -> Option<i32> {
+        use std::convert::TryFrom;
+        value.as_i64().and_then(|value| i32::try_from(value).ok())
+    }
+}
+
+impl PositionalField for u32 {
+    const EXPECTED: &'static str = "32 bit unsigned integer";
+
+    fn from_json(value: &serde_json::Value) -> Option<u32> {
+        use std::convert::TryFrom;
+        value.as_u64().and_then(|value| u32::try_from(value).ok())
+    }
+}
+
+impl PositionalField for String {
+    const EXPECTED: &'static str = "string";
+
+    fn from_json(value: &serde_json::Value) -> Option<String> {
+        value.as_str().map(|value| value.to_owned())
+    }
+}
+
+impl PositionalField for chrono::DateTime<chrono::Utc> {
+    const EXPECTED: &'static str = "timestamp in milliseconds";
+
+    fn from_json(value: &serde_json::Value) -> Option<chrono::DateTime<chrono::Utc>> {
+        use chrono::TimeZone;
+        value
+            .as_i64()
+            .and_then(|milliseconds| chrono::Utc.timestamp_millis_opt(milliseconds).single())
+    }
+}
+
+impl PositionalField for crate::coin::Coin {
+    const EXPECTED: &'static str = "coin";
+
+    fn from_json(value: &serde_json::Value) -> Option<crate::coin::Coin> {
+        value.as_str().map(crate::coin::Coin::from)
+    }
+}
+
+impl PositionalField for crate::coin::Coins {
+    const EXPECTED: &'static str = "supported market symbol";
+
+    fn from_json(value: &serde_json::Value) -> Option<crate::coin::Coins> {
+        use std::convert::TryFrom;
+        value.as_str().and_then(|value| crate::coin::Coins::try_from(value).ok())
+    }
+}
+
+impl PositionalField for crate::base::Target {
+    const EXPECTED: &'static str = "order type";
+
+    fn from_json(value: &serde_json::Value) -> Option<crate::base::Target> {
+        use std::convert::TryFrom;
+        value.as_str().and_then(|value| crate::base::Target::try_from(value).ok())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::ParseError;
+
+    crate::positional_struct! {
+        #[derive(Debug)]
+        struct Row {
+            0 => id: i32,
+            1 => price: f64,
+            3 => count: u32,
+            4 => note: Option<String>,
+        }
+    }
+
+    fn parse(json: &str) -> Result<Row, ParseError> {
+        Row::from_values(&serde_json::from_str::<Vec<serde_json::Value>>(json).expect("Invalid json"))
+    }
+
+    #[test]
+    fn reads_each_field_from_its_position() {
+        let row = parse(r#"[7, "2.5", null, 3]"#).expect("Failed to parse row");
+        assert_eq!((row.id, row.price, row.count, row.note), (7, 2.5, 3, None));
+    }
+
+    #[test]
+    fn missing_position_reports_its_index_and_expected_type() {
+        let error = parse(r#"[7, "2.5"]"#).expect_err("Parsed a row without a count");
+        match &error {
+            ParseError::MissingField { index, field, expected } => {
+                assert_eq!((*index, *field, *expected), (3, "count", "32 bit unsigned integer"));
+            }
+            error => panic!("Unexpected error: {:?}", error),
+        }
+        assert_eq!(
+            error.to_string(),
+            "Missing field `count` at index 3, expected 32 bit unsigned integer"
+        );
+    }
+
+    #[test]
+    fn wrongly_typed_position_reports_what_was_found() {
+        let error = parse(r#"[7, true, null, 3]"#).expect_err("Parsed a boolean price");
+        match &error {
+            ParseError::InvalidField { index, field, expected, found } => {
+                assert_eq!((*index, *field, *expected), (1, "price", "number or numeric string"));
+                assert_eq!(*found, serde_json::Value::Bool(true));
+            }
+            error => panic!("Unexpected error: {:?}", error),
+        }
+        assert_eq!(
+            error.to_string(),
+            "Invalid field `price` at index 1, expected number or numeric string, found true"
+        );
+    }
+}
@@ -1,29 +1,98 @@
 use super::base;
 use super::extractor;
+use super::dispatch::Dispatcher;
+use super::response::WithMetadata;
 
-pub struct KunaPublicClient<TConnector> {
-    client: std::sync::Arc<hyper::Client<TConnector>>,
+pub struct KunaPublicClient {
+    dispatcher: Dispatcher,
     base_url: url::Url,
 }
 
-impl<TConnector> KunaPublicClient<TConnector> 
-where 
-    TConnector: hyper::client::connect::Connect + Send + Sync + Clone + 'static
-{
+impl KunaPublicClient {
     pub fn new(
-        client: std::sync::Arc<hyper::Client<TConnector>>,
+        transport: std::sync::Arc<dyn crate::transport::Transport>,
         base_url: url::Url,
-    ) -> KunaPublicClient<TConnector> {
+    ) -> KunaPublicClient {
         KunaPublicClient {
-            client,
+            dispatcher: Dispatcher::new(transport),
             base_url,
         }
     }
 
+    pub fn with_rate_limiter(
+        mut self,
+        rate_limiter: std::sync::Arc<crate::limiter::RateLimiter>,
+    ) -> KunaPublicClient {
+        self.dispatcher.rate_limiter = Some(rate_limiter);
+        self
+    }
+
+    pub fn with_retry_policy(
+        mut self,
+        retry_policy: crate::retry::RetryPolicy,
+    ) -> KunaPublicClient {
+        self.dispatcher.retry_policy = retry_policy;
+        self
+    }
+
+    /// Appends `middleware` to the chain run around every request.
+    pub fn with_middleware(
+        mut self,
+        middleware: std::sync::Arc<dyn crate::middleware::Middleware>,
+    ) -> KunaPublicClient {
+        self.dispatcher.middleware.push(middleware);
+        self
+    }
+
+    pub fn with_middleware_chain(
+        mut self,
+        middleware: crate::middleware::MiddlewareChain,
+    ) -> KunaPublicClient {
+        self.dispatcher.middleware = middleware;
+        self
+    }
+
+    /// Shares the exchange reported quota, and the pauses it causes, with other clients of the key.
+    pub fn with_quota_tracker(
+        mut self,
+        quota: std::sync::Arc<crate::limiter::QuotaTracker>,
+    ) -> KunaPublicClient {
+        self.dispatcher.quota = quota;
+        self
+    }
+
+    pub fn rate_limiter(&self) -> Option<&std::sync::Arc<crate::limiter::RateLimiter>> {
+        self.dispatcher.rate_limiter.as_ref()
+    }
+
+    pub fn retry_policy(&self) -> &crate::retry::RetryPolicy {
+        &self.dispatcher.retry_policy
+    }
+
+    pub fn middleware(&self) -> &crate::middleware::MiddlewareChain {
+        &self.dispatcher.middleware
+    }
+
+    pub fn quota_tracker(&self) -> &std::sync::Arc<crate::limiter::QuotaTracker> {
+        &self.dispatcher.quota
+    }
+
+    /// The last quota the exchange reported for public endpoints.
+    pub fn rate_limit_status(&self) -> Option<crate::response::RateLimitStatus> {
+        self.dispatcher.quota.status(base::Endpoint::Markets)
+    }
+
     pub async fn get_orderbook(
         &self,
         coins: crate::coin::Coins
     ) -> Result<crate::models::OrderBook, String> {
+        self.get_orderbook_with_metadata(coins).await.map(WithMetadata::into_inner)
+    }
+
+    pub async fn get_orderbook_with_metadata(
+        &self,
+        coins: crate::coin::Coins
+    ) -> Result<WithMetadata<crate::models::OrderBook>, String> {
         let coins_string = coins.to_string();
         let mut url = self.base_url.clone();
         url.path_segments_mut()
@@ -31,48 +100,48 @@ where
             .push(base::VERSION)
             .push(base::BOOK)
             .push(&coins_string);
-        let request = match base::default_request_builder(&url)
-            .method(hyper::Method::GET)
-            .body(hyper::Body::empty()) {
-            Ok(request) => request,
-            Err(error) => return Err(format!("Failed to create request: {:#?}", error)),
-        };
-        let (_header, body) = match self
-            .client
-            .request(request)
-            .await {
-            Ok(response) => response.into_parts(),
-            Err(error) => return Err(format!("Failed to create response: {:#?}", error)),
-        };
+        let response = self.dispatcher.send(base::Endpoint::OrderBook, Some(&coins_string), || {
+            (
+                base::default_request_builder(&url)
+                    .method(http::Method::GET),
+                Vec::new(),
+            )
+        })
+        .await?;
         use crate::models::OrderBookEntries;
-        let order_book_entries = match extractor::read_body::<OrderBookEntries>(body)
-            .await {
+        let order_book_entries = match extractor::parse_body::<OrderBookEntries>(&response.body) {
             Some(order_book) => order_book,
             None => return Err("Failed to read body: {:#?}".to_owned()),
         };
-        Ok(crate::models::OrderBook::with(coins, order_book_entries))
+        Ok(WithMetadata {
+            value: crate::models::OrderBook::with(coins, order_book_entries),
+            metadata: response.into_metadata(),
+        })
     }
 
     pub async fn get_markets(&self) -> Result<crate::models::Markets, String> {
+        self.get_markets_with_metadata().await.map(WithMetadata::into_inner)
+    }
+
+    pub async fn get_markets_with_metadata(&self) -> Result<WithMetadata<crate::models::Markets>, String> {
         let mut url = self.base_url.clone();
         url.path_segments_mut()
             .expect("Invalid url")
             .push(base::VERSION)
             .push(base::MARKETS);
-        let request = match base::default_request_builder(&url)
-            .method(hyper::Method::GET)
-            .body(hyper::Body::empty()) {
-            Ok(request) => request,
-            Err(error) => return Err(format!("Failed to create request: {:#?}", error)),
-        };
-        let (_header, body) = match self.client
-            .request(request)
-            .await {
-            Ok(response) => response.into_parts(),
-            Err(error) => return Err(format!("Failed to create response: {:#?}", error)),
-        };
-        match extractor::read_body::<crate::models::Markets>(body).await {
-            Some(result) => Ok(result),
+        let response = self.dispatcher.send(base::Endpoint::Markets, None, || {
+            (
+                base::default_request_builder(&url)
+                    .method(http::Method::GET),
+                Vec::new(),
+            )
+        })
+        .await?;
+        match extractor::parse_body::<crate::models::Markets>(&response.body) {
+            Some(result) => Ok(WithMetadata {
+                value: result,
+                metadata: response.into_metadata(),
+            }),
             None => Err("Failed to deserailize the body".to_owned()),
         }
     }
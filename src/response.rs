@@ -0,0 +1,266 @@
+use std::time::Duration;
+
+/// Whether `name` is a quota header, `retry-after` or one of the `x-ratelimit-*`
+/// and `ratelimit-*` families. Header names are matched case insensitively.
+pub fn is_rate_limit_header(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    name == "retry-after" || name.starts_with("x-ratelimit-") || name.starts_with("ratelimit")
+}
+
+/// Quota information sent by the exchange, from the `x-ratelimit-*` or `ratelimit-*`
+/// headers and `retry-after`. Durations are relative to the response `Date`, or to the
+/// local clock when it is missing, and capped at `limiter::MAX_RATE_LIMITED_PAUSE`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RateLimitStatus {
+    pub limit: Option<u64>,
+    pub remaining: Option<u64>,
+    // Time until the quota window resets.
+    pub reset: Option<Duration>,
+    pub retry_after: Option<Duration>,
+}
+
+impl RateLimitStatus {
+    /// Returns `None` if the response carries none of the headers.
+    pub fn from_headers(headers: &http::HeaderMap) -> Option<RateLimitStatus> {
+        let now = match server_date(headers) {
+            Some(date) => date,
+            None => chrono::Utc::now(),
+        };
+        let status = RateLimitStatus {
+            limit: quota_header(headers, "limit").and_then(leading_number),
+            remaining: quota_header(headers, "remaining").and_then(leading_number),
+            reset: quota_header(headers, "reset").and_then(|value| reset_delay(value, now)),
+            retry_after: header_str(headers, "retry-after").and_then(|value| retry_after_delay(value, now)),
+        };
+        match status == RateLimitStatus::default() {
+            true => None,
+            false => Some(status),
+        }
+    }
+
+    pub fn is_exhausted(&self) -> bool {
+        self.remaining == Some(0)
+    }
+
+    /// How long requests should be held back: `retry_after` if set, otherwise
+    /// the time until `reset` once the quota is exhausted.
+    pub fn pause(&self) -> Option<Duration> {
+        match self.retry_after {
+            Some(retry_after) => Some(retry_after),
+            None if self.is_exhausted() => self.reset,
+            None => None,
+        }
+    }
+}
+
+fn header_str<'a>(headers: &'a http::HeaderMap, name: &str) -> Option<&'a str> {
+    headers.get(name).and_then(|value| value.to_str().ok())
+}
+
+fn quota_header<'a>(headers: &'a http::HeaderMap, field: &str) -> Option<&'a str> {
+    header_str(headers, &format!("x-ratelimit-{}", field)).or_else(|| header_str(headers, &format!("ratelimit-{}", field)))
+}
+
+// Values like `100`, `100, 100;w=60` or `0.5` keep only their first number.
+fn leading_number(value: &str) -> Option<u64> {
+    let value = value.split([',', ';']).next()?.trim();
+    match value.parse::<u64>() {
+        Ok(number) => Some(number),
+        Err(_) => value.parse::<f64>().ok().filter(|number| *number >= 0.0).map(|number| number as u64),
+    }
+}
+
+// Either seconds until the reset or a unix timestamp in seconds or milliseconds.
+fn reset_delay(value: &str, now: chrono::DateTime<chrono::Utc>) -> Option<Duration> {
+    use std::convert::TryFrom;
+    let reset = leading_number(value)?;
+    let reset_at = if reset >= 1_000_000_000_000 {
+        i64::try_from(reset).unwrap_or(i64::MAX)
+    } else if reset >= 1_000_000_000 {
+        reset as i64 * 1000
+    } else {
+        return Some(Duration::from_secs(reset).min(crate::limiter::MAX_RATE_LIMITED_PAUSE));
+    };
+    Some(until(reset_at, now))
+}
+
+// Either seconds or an HTTP date.
+fn retry_after_delay(value: &str, now: chrono::DateTime<chrono::Utc>) -> Option<Duration> {
+    if let Ok(seconds) = value.trim().parse::<f64>() {
+        return match Duration::try_from_secs_f64(seconds) {
+            Ok(delay) => Some(delay.min(crate::limiter::MAX_RATE_LIMITED_PAUSE)),
+            // Too large for a `Duration` but still a wait.
+            Err(_) if seconds > 0.0 => Some(crate::limiter::MAX_RATE_LIMITED_PAUSE),
+            Err(_) => None,
+        };
+    }
+    let retry_at = chrono::DateTime::parse_from_rfc2822(value.trim()).ok()?;
+    Some(until(retry_at.timestamp_millis(), now))
+}
+
+// Time from `now` to a unix timestamp in milliseconds, capped at `MAX_RATE_LIMITED_PAUSE`.
+fn until(timestamp: i64, now: chrono::DateTime<chrono::Utc>) -> Duration {
+    let delay = timestamp.saturating_sub(now.timestamp_millis()).max(0) as u64;
+    Duration::from_millis(delay).min(crate::limiter::MAX_RATE_LIMITED_PAUSE)
+}
+
+fn server_date(headers: &http::HeaderMap) -> Option<chrono::DateTime<chrono::Utc>> {
+    let date = header_str(headers, http::header::DATE.as_str())?;
+    chrono::DateTime::parse_from_rfc2822(date)
+        .ok()
+        .map(|date| date.with_timezone(&chrono::Utc))
+}
+
+/// The response head, body and timing of the attempt that produced a typed result.
+#[derive(Clone)]
+pub struct ResponseMetadata {
+    pub status: http::StatusCode,
+    pub headers: http::HeaderMap,
+    // The server `Date` header, `None` if it is missing or not an RFC 2822 date.
+    pub date: Option<chrono::DateTime<chrono::Utc>>,
+    pub rate_limit_headers: http::HeaderMap,
+    pub rate_limit: Option<RateLimitStatus>,
+    pub body: Vec<u8>,
+    // From handing the request to the transport until the whole body was read.
+    pub round_trip: Duration,
+    // Attempts made by the dispatcher, retries included.
+    pub attempts: u32,
+}
+
+impl ResponseMetadata {
+    pub fn new(
+        status: http::StatusCode,
+        headers: http::HeaderMap,
+        body: Vec<u8>,
+        round_trip: Duration,
+        attempts: u32,
+    ) -> ResponseMetadata {
+        let date = server_date(&headers);
+        let rate_limit = RateLimitStatus::from_headers(&headers);
+        let rate_limit_headers = headers
+            .iter()
+            .filter(|(name, _)| is_rate_limit_header(name.as_str()))
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect();
+        ResponseMetadata {
+            status,
+            headers,
+            date,
+            rate_limit_headers,
+            rate_limit,
+            body,
+            round_trip,
+            attempts,
+        }
+    }
+
+    pub fn body_text(&self) -> std::borrow::Cow<'_, str> {
+        String::from_utf8_lossy(&self.body)
+    }
+}
+
+impl std::fmt::Debug for ResponseMetadata {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        formatter
+            .debug_struct("ResponseMetadata")
+            .field("status", &self.status)
+            .field("headers", &crate::base::RedactedHeaders(&self.headers))
+            .field("date", &self.date)
+            .field("rate_limit_headers", &self.rate_limit_headers)
+            .field("rate_limit", &self.rate_limit)
+            .field("body", &self.body_text())
+            .field("round_trip", &self.round_trip)
+            .field("attempts", &self.attempts)
+            .finish()
+    }
+}
+
+/// A typed result together with the response it was parsed from, returned by
+/// the `*_with_metadata` variants of the client endpoints.
+#[derive(Clone, Debug)]
+pub struct WithMetadata<T> {
+    pub value: T,
+    pub metadata: ResponseMetadata,
+}
+
+impl<T> WithMetadata<T> {
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+
+    pub fn map<U, F: FnOnce(T) -> U>(self, f: F) -> WithMetadata<U> {
+        WithMetadata {
+            value: f(self.value),
+            metadata: self.metadata,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::limiter::MAX_RATE_LIMITED_PAUSE;
+
+    fn headers(pairs: &[(&'static str, &str)]) -> http::HeaderMap {
+        let mut headers = http::HeaderMap::new();
+        for (name, value) in pairs {
+            headers.insert(*name, http::HeaderValue::from_str(value).expect("Invalid header value"));
+        }
+        headers
+    }
+
+    #[test]
+    fn parses_quota_headers() {
+        let status = RateLimitStatus::from_headers(&headers(&[
+            ("date", "Tue, 14 Nov 2023 22:13:20 GMT"),
+            ("x-ratelimit-limit", "100, 100;w=60"),
+            ("x-ratelimit-remaining", "0"),
+            ("x-ratelimit-reset", "1700000030"),
+        ]))
+        .expect("Missing quota");
+        assert_eq!(status.limit, Some(100));
+        assert_eq!(status.remaining, Some(0));
+        assert_eq!(status.reset, Some(Duration::from_secs(30)));
+        assert_eq!(status.retry_after, None);
+        assert!(status.is_exhausted());
+        assert_eq!(status.pause(), Some(Duration::from_secs(30)));
+
+        let status = RateLimitStatus::from_headers(&headers(&[
+            ("ratelimit-remaining", "7"),
+            ("ratelimit-reset", "12"),
+            ("retry-after", "0.5"),
+        ]))
+        .expect("Missing quota");
+        assert_eq!(status.limit, None);
+        assert_eq!(status.remaining, Some(7));
+        assert_eq!(status.reset, Some(Duration::from_secs(12)));
+        assert_eq!(status.pause(), Some(Duration::from_millis(500)));
+
+        assert_eq!(RateLimitStatus::from_headers(&headers(&[("content-type", "application/json")])), None);
+    }
+
+    #[test]
+    fn retry_after_accepts_an_http_date() {
+        let status = RateLimitStatus::from_headers(&headers(&[
+            ("date", "Tue, 14 Nov 2023 22:13:20 GMT"),
+            ("retry-after", "Tue, 14 Nov 2023 22:13:25 GMT"),
+        ]))
+        .expect("Missing quota");
+        assert_eq!(status.retry_after, Some(Duration::from_secs(5)));
+    }
+
+    #[test]
+    fn bogus_delays_are_capped_instead_of_panicking() {
+        for value in ["1e30", "1e400", "18446744073709551616"] {
+            let status = RateLimitStatus::from_headers(&headers(&[("retry-after", value)])).expect("Missing quota");
+            assert_eq!(status.retry_after, Some(MAX_RATE_LIMITED_PAUSE), "{}", value);
+        }
+        for value in ["-1", "NaN", "-inf"] {
+            assert_eq!(RateLimitStatus::from_headers(&headers(&[("retry-after", value)])), None, "{}", value);
+        }
+        for value in ["18446744073709551615", "9223372036854775808", "999999999999"] {
+            let status = RateLimitStatus::from_headers(&headers(&[("x-ratelimit-reset", value)])).expect("Missing quota");
+            assert_eq!(status.reset, Some(MAX_RATE_LIMITED_PAUSE), "{}", value);
+        }
+    }
+}
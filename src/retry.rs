@@ -0,0 +1,138 @@
+use std::time::Duration;
+
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    // Between 0 and 1, see `with_jitter`.
+    jitter: f64,
+    // Lets `KunaClient::create_order` look for and resend failed submissions.
+    pub reconcile_orders: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(200),
+            max_delay: Duration::from_secs(5),
+            jitter: 0.5,
+            reconcile_orders: true,
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn none() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 1,
+            ..RetryPolicy::default()
+        }
+    }
+
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> RetryPolicy {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    pub fn with_delays(mut self, base_delay: Duration, max_delay: Duration) -> RetryPolicy {
+        self.base_delay = base_delay;
+        self.max_delay = max_delay;
+        self
+    }
+
+    /// The share of each delay that may be randomly removed, clamped to between 0 and 1
+    /// with NaN taken as 0.
+    pub fn with_jitter(mut self, jitter: f64) -> RetryPolicy {
+        self.jitter = if jitter.is_nan() { 0.0 } else { jitter.clamp(0.0, 1.0) };
+        self
+    }
+
+    pub fn jitter(&self) -> f64 {
+        self.jitter
+    }
+
+    /// See `KunaClient::create_order` for what reconciliation costs and its limits.
+    pub fn with_order_reconciliation(mut self, reconcile_orders: bool) -> RetryPolicy {
+        self.reconcile_orders = reconcile_orders;
+        self
+    }
+
+    pub fn retries_enabled(&self) -> bool {
+        self.max_attempts > 1
+    }
+
+    pub fn reconciles_orders(&self) -> bool {
+        self.reconcile_orders && self.retries_enabled()
+    }
+
+    /// Only idempotent endpoints are retried blindly, see `base::Endpoint::is_idempotent`.
+    pub fn should_retry(&self, endpoint: crate::base::Endpoint, attempt: u32) -> bool {
+        endpoint.is_idempotent() && attempt < self.max_attempts
+    }
+
+    /// Exponential backoff for the given 1-based attempt with up to `jitter` of it randomly removed.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(16);
+        let delay = self
+            .base_delay
+            .checked_mul(1 << exponent)
+            .unwrap_or(self.max_delay)
+            .min(self.max_delay);
+        delay.mul_f64(1.0 - self.jitter * jitter_factor())
+    }
+}
+
+fn jitter_factor() -> f64 {
+    use std::hash::BuildHasher;
+    use std::hash::Hasher;
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|duration| duration.subsec_nanos())
+        .unwrap_or(0);
+    let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+    hasher.write_u32(nanos);
+    (hasher.finish() % 10_000) as f64 / 10_000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn delays_back_off_exponentially_up_to_the_maximum() {
+        let policy = RetryPolicy::default()
+            .with_delays(Duration::from_millis(100), Duration::from_millis(350))
+            .with_jitter(0.0);
+        let delays: Vec<_> = (1..=4).map(|attempt| policy.delay(attempt)).collect();
+        assert_eq!(
+            delays,
+            vec![
+                Duration::from_millis(100),
+                Duration::from_millis(200),
+                Duration::from_millis(350),
+                Duration::from_millis(350),
+            ]
+        );
+        assert_eq!(policy.delay(u32::MAX), Duration::from_millis(350));
+    }
+
+    #[test]
+    fn invalid_jitter_is_clamped() {
+        for (jitter, expected) in [(f64::NAN, 0.0), (-1.0, 0.0), (0.25, 0.25), (2.0, 1.0), (f64::INFINITY, 1.0)] {
+            let policy = RetryPolicy::default().with_jitter(jitter);
+            assert_eq!(policy.jitter(), expected);
+            for attempt in 1..=3 {
+                assert!(policy.delay(attempt) <= policy.max_delay);
+            }
+        }
+    }
+
+    #[test]
+    fn orders_are_reconciled_by_default_when_retrying() {
+        assert!(RetryPolicy::default().reconciles_orders());
+        assert!(!RetryPolicy::none().reconciles_orders());
+        assert!(!RetryPolicy::default().with_order_reconciliation(false).reconciles_orders());
+    }
+}
@@ -0,0 +1,235 @@
+use crate::transport::BoxFuture;
+use hmac::Mac;
+use hmac::NewMac;
+use std::time::Duration;
+
+pub const DEFAULT_NONCE_WINDOW: Duration = Duration::from_secs(30);
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum VerificationError {
+    MissingHeader(&'static str),
+    InvalidNonce,
+    // The nonce, in milliseconds, is further from `now` than the allowed window.
+    StaleNonce {
+        nonce: i64,
+        now: i64,
+    },
+    InvalidSignature,
+}
+
+impl std::fmt::Display for VerificationError {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VerificationError::MissingHeader(name) => write!(formatter, "Missing header {}", name),
+            VerificationError::InvalidNonce => write!(formatter, "Invalid nonce"),
+            VerificationError::StaleNonce { nonce, now } => {
+                write!(formatter, "Nonce {} is outside the allowed window at {}", nonce, now)
+            },
+            VerificationError::InvalidSignature => write!(formatter, "Invalid signature"),
+        }
+    }
+}
+
+impl std::error::Error for VerificationError {}
+
+/// Produces the `kun-signature` header for a signing message built by `base::signing_message`,
+/// i.e. `path + nonce + body` with `{}` as the body of requests without one.
+///
+/// Implement it to keep the private key out of the process, e.g. in a signing service.
+/// The signature is the lowercase hex encoded HMAC-SHA384 of the message keyed with the
+/// private key. Test vectors:
+///
+/// | private key | message | signature |
+/// |-------------|---------|-----------|
+/// | `secret` | `/v3/auth/r/wallets1700000000000{}` | `4c4438482db8513636454dc52fa54ef717f76b5f8c7a285572d767108e32bc07bce56cb51f843dbcde80387664842ab2` |
+/// | `secret` | `/v3/auth/w/order/submit1700000000000{"symbol":"tonusdt","amount":1.5,"price":2.5,"type":"limit"}` | `310fcfecec59690f1c9d719264c7c159d17293d2be4d4cfa373c79c0e609bae02cfb27fef27a5f8e7f59e2d91f93b265` |
+/// | `another-key` | `/v3/order/cancel1700000000123{"order_id":42}` | `0caf6cc883dc5bc5f4578af569da4159a8bf0176d76e5adaf6c1fc873361cc1cccc5d52b9cb6758bad4bd16cf40becb1` |
+pub trait Signer: Send + Sync {
+    fn sign<'a>(&'a self, message: &'a str) -> BoxFuture<'a, Result<String, String>>;
+}
+
+impl<T: Signer + ?Sized> Signer for std::sync::Arc<T> {
+    fn sign<'a>(&'a self, message: &'a str) -> BoxFuture<'a, Result<String, String>> {
+        (**self).sign(message)
+    }
+}
+
+/// Signs in process with the private key, the default signer of `AuthContext`.
+pub struct HmacSigner {
+    private_key: zeroize::Zeroizing<String>,
+}
+
+impl HmacSigner {
+    pub fn new(private_key: String) -> HmacSigner {
+        HmacSigner {
+            private_key: zeroize::Zeroizing::new(private_key),
+        }
+    }
+
+    fn mac(&self, message: &str) -> hmac::Hmac<sha2::Sha384> {
+        let mut mac = hmac::Hmac::<sha2::Sha384>::new_from_slice(
+            self.private_key.as_bytes())
+            .expect("HMAC can take key of any size");
+        mac.update(message.as_bytes());
+        mac
+    }
+
+    pub fn sign_message(&self, message: &str) -> String {
+        hex::encode(self.mac(message).finalize().into_bytes())
+    }
+
+    /// Checks a signature made by `base::sign_request` in constant time. The nonce must be
+    /// a millisecond timestamp within `window` of the current time in either direction.
+    pub fn verify(
+        &self,
+        path: &str,
+        nonce: &str,
+        body: &[u8],
+        signature: &str,
+        window: Duration,
+    ) -> Result<(), VerificationError> {
+        let nonce = match nonce.parse::<i64>() {
+            Ok(nonce) => nonce,
+            Err(_) => return Err(VerificationError::InvalidNonce),
+        };
+        use std::convert::TryFrom;
+        let now = chrono::Utc::now().timestamp_millis();
+        // The nonce is untrusted, so neither the distance nor the window may overflow.
+        let window = u64::try_from(window.as_millis()).unwrap_or(u64::MAX);
+        if now.abs_diff(nonce) > window {
+            return Err(VerificationError::StaleNonce { nonce, now });
+        }
+        let signature = match hex::decode(signature) {
+            Ok(signature) => signature,
+            Err(_) => return Err(VerificationError::InvalidSignature),
+        };
+        let message = crate::base::signing_message(path, nonce, body);
+        self.mac(&message)
+            .verify(&signature)
+            .map_err(|_| VerificationError::InvalidSignature)
+    }
+
+    /// `verify` with the nonce and signature taken from the `kun-nonce` and `kun-signature` headers.
+    pub fn verify_headers(
+        &self,
+        path: &str,
+        headers: &http::HeaderMap,
+        body: &[u8],
+        window: Duration,
+    ) -> Result<(), VerificationError> {
+        let header = |name: &'static str| match headers.get(name).map(|value| value.to_str()) {
+            Some(Ok(value)) => Ok(value),
+            _ => Err(VerificationError::MissingHeader(name)),
+        };
+        self.verify(path, header("kun-nonce")?, body, header("kun-signature")?, window)
+    }
+}
+
+/// Verifies a request signed with `secret`, see `HmacSigner::verify`.
+pub fn verify_signature(
+    secret: &str,
+    path: &str,
+    nonce: &str,
+    body: &[u8],
+    signature: &str,
+    window: Duration,
+) -> Result<(), VerificationError> {
+    HmacSigner::new(secret.to_owned()).verify(path, nonce, body, signature, window)
+}
+
+impl Signer for HmacSigner {
+    fn sign<'a>(&'a self, message: &'a str) -> BoxFuture<'a, Result<String, String>> {
+        let signature = self.sign_message(message);
+        Box::pin(async move { Ok(signature) })
+    }
+}
+
+impl std::fmt::Debug for HmacSigner {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        formatter
+            .debug_struct("HmacSigner")
+            .field("private_key", &crate::base::REDACTED)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The vectors documented on `Signer`.
+    const VECTORS: [(&str, &str, i64, &str, &str); 3] = [
+        (
+            "secret",
+            "/v3/auth/r/wallets",
+            1700000000000,
+            "",
+            "4c4438482db8513636454dc52fa54ef717f76b5f8c7a285572d767108e32bc07bce56cb51f843dbcde80387664842ab2",
+        ),
+        (
+            "secret",
+            "/v3/auth/w/order/submit",
+            1700000000000,
+            r#"{"symbol":"tonusdt","amount":1.5,"price":2.5,"type":"limit"}"#,
+            "310fcfecec59690f1c9d719264c7c159d17293d2be4d4cfa373c79c0e609bae02cfb27fef27a5f8e7f59e2d91f93b265",
+        ),
+        (
+            "another-key",
+            "/v3/order/cancel",
+            1700000000123,
+            r#"{"order_id":42}"#,
+            "0caf6cc883dc5bc5f4578af569da4159a8bf0176d76e5adaf6c1fc873361cc1cccc5d52b9cb6758bad4bd16cf40becb1",
+        ),
+    ];
+
+    // Wide enough to accept the fixed nonces of the vectors.
+    const CENTURY: Duration = Duration::from_secs(100 * 365 * 24 * 60 * 60);
+
+    #[test]
+    fn signs_test_vectors() {
+        assert_eq!(crate::base::signing_message("/v3/auth/r/wallets", 1700000000000, b""), "/v3/auth/r/wallets1700000000000{}");
+        for (key, path, nonce, body, signature) in VECTORS.iter() {
+            let signer = HmacSigner::new(key.to_string());
+            let message = crate::base::signing_message(path, *nonce, body.as_bytes());
+            assert_eq!(signer.sign_message(&message), *signature, "{}", message);
+            assert_eq!(signer.verify(path, &nonce.to_string(), body.as_bytes(), signature, CENTURY), Ok(()));
+        }
+    }
+
+    #[test]
+    fn rejects_altered_or_stale_requests() {
+        let (key, path, nonce, body, signature) = VECTORS[1];
+        let signer = HmacSigner::new(key.to_owned());
+        let nonce = nonce.to_string();
+        assert_eq!(
+            signer.verify(path, &nonce, br#"{"symbol":"tonusdt","amount":15,"price":2.5,"type":"limit"}"#, signature, CENTURY),
+            Err(VerificationError::InvalidSignature)
+        );
+        assert_eq!(
+            HmacSigner::new("other".to_owned()).verify(path, &nonce, body.as_bytes(), signature, CENTURY),
+            Err(VerificationError::InvalidSignature)
+        );
+        assert!(matches!(
+            signer.verify(path, &nonce, body.as_bytes(), signature, DEFAULT_NONCE_WINDOW),
+            Err(VerificationError::StaleNonce { .. })
+        ));
+    }
+
+    #[test]
+    fn rejects_extreme_nonces_without_overflowing() {
+        let signer = HmacSigner::new("secret".to_owned());
+        for nonce in [i64::MIN, i64::MIN + 1, i64::MAX, -1] {
+            let message = crate::base::signing_message("/v3/auth/r/wallets", nonce, b"");
+            let signature = signer.sign_message(&message);
+            assert!(matches!(
+                signer.verify("/v3/auth/r/wallets", &nonce.to_string(), b"", &signature, DEFAULT_NONCE_WINDOW),
+                Err(VerificationError::StaleNonce { .. })
+            ));
+            assert_eq!(signer.verify("/v3/auth/r/wallets", &nonce.to_string(), b"", &signature, Duration::MAX), Ok(()));
+        }
+        assert_eq!(
+            signer.verify("/v3/auth/r/wallets", "-9223372036854775809", b"", "00", DEFAULT_NONCE_WINDOW),
+            Err(VerificationError::InvalidNonce)
+        );
+    }
+}
@@ -0,0 +1,100 @@
+//! Request instrumentation, enabled by the `tracing` and `metrics` features.
+//!
+//! With `tracing` every endpoint call runs in a `kuna_call` span (`endpoint`, `market`) and
+//! every attempt in a nested `kuna_attempt` span (`attempt`, `status`, `latency_ms`,
+//! `response_size`). Headers are never recorded, so keys and signatures cannot leak.
+//!
+//! With `metrics` every attempt updates the metrics below, labelled with `endpoint`
+//! and `status` (the HTTP status code, or `error` when no response was received).
+
+use crate::base::Endpoint;
+use std::future::Future;
+
+/// Counter of attempts.
+pub const REQUESTS_TOTAL: &str = "kuna_requests_total";
+/// Counter of attempts that failed with a transport error or a 4xx/5xx status.
+pub const REQUEST_ERRORS_TOTAL: &str = "kuna_request_errors_total";
+/// Histogram of attempt latency in seconds.
+pub const REQUEST_DURATION_SECONDS: &str = "kuna_request_duration_seconds";
+/// Histogram of response body size in bytes.
+pub const RESPONSE_SIZE_BYTES: &str = "kuna_response_size_bytes";
+
+type AttemptResult = Result<crate::transport::Response, crate::transport::TransportError>;
+
+/// Runs a whole endpoint call, retries included.
+pub(crate) async fn call<F: Future>(endpoint: Endpoint, market: Option<&str>, future: F) -> F::Output {
+    #[cfg(feature = "tracing")]
+    {
+        let span = tracing::info_span!("kuna_call", endpoint = %endpoint, market = market.unwrap_or(""));
+        tracing::Instrument::instrument(future, span).await
+    }
+    #[cfg(not(feature = "tracing"))]
+    {
+        let _ = (endpoint, market);
+        future.await
+    }
+}
+
+/// Runs one transport round trip and records its outcome.
+pub(crate) async fn attempt<F>(endpoint: Endpoint, attempt: u32, future: F) -> AttemptResult
+where
+    F: Future<Output = AttemptResult>,
+{
+    let started = std::time::Instant::now();
+    #[cfg(feature = "tracing")]
+    let span = tracing::info_span!(
+        "kuna_attempt",
+        attempt,
+        status = tracing::field::Empty,
+        latency_ms = tracing::field::Empty,
+        response_size = tracing::field::Empty,
+    );
+    #[cfg(feature = "tracing")]
+    let result = tracing::Instrument::instrument(future, span.clone()).await;
+    #[cfg(not(feature = "tracing"))]
+    let result = future.await;
+    let latency = started.elapsed();
+    #[cfg(feature = "tracing")]
+    record_span(&span, &result, latency);
+    #[cfg(feature = "metrics")]
+    record_metrics(endpoint, &result, latency);
+    let _ = (endpoint, attempt, latency);
+    result
+}
+
+#[cfg(feature = "tracing")]
+fn record_span(span: &tracing::Span, result: &AttemptResult, latency: std::time::Duration) {
+    span.record("latency_ms", latency.as_millis() as u64);
+    match result {
+        Ok(response) => {
+            span.record("status", response.status().as_u16());
+            span.record("response_size", response.body().len() as u64);
+            if response.status().is_client_error() || response.status().is_server_error() {
+                tracing::warn!(parent: span, status = response.status().as_u16(), "kuna request failed");
+            } else {
+                tracing::debug!(parent: span, "kuna request completed");
+            }
+        },
+        Err(error) => tracing::warn!(parent: span, error = %error, "kuna request failed"),
+    }
+}
+
+#[cfg(feature = "metrics")]
+fn record_metrics(endpoint: Endpoint, result: &AttemptResult, latency: std::time::Duration) {
+    let (status, failed) = match result {
+        Ok(response) => (
+            response.status().as_u16().to_string(),
+            response.status().is_client_error() || response.status().is_server_error(),
+        ),
+        Err(_) => ("error".to_owned(), true),
+    };
+    let endpoint = endpoint.to_string();
+    metrics::counter!(REQUESTS_TOTAL, "endpoint" => endpoint.clone(), "status" => status.clone()).increment(1);
+    if failed {
+        metrics::counter!(REQUEST_ERRORS_TOTAL, "endpoint" => endpoint.clone(), "status" => status).increment(1);
+    }
+    metrics::histogram!(REQUEST_DURATION_SECONDS, "endpoint" => endpoint.clone()).record(latency.as_secs_f64());
+    if let Ok(response) = result {
+        metrics::histogram!(RESPONSE_SIZE_BYTES, "endpoint" => endpoint).record(response.body().len() as f64);
+    }
+}
@@ -0,0 +1,100 @@
+/// Future returned by the object safe async traits of the crate.
+pub type BoxFuture<'a, T> = std::pin::Pin<Box<dyn std::future::Future<Output = T> + Send + 'a>>;
+
+pub type Request = http::Request<Vec<u8>>;
+pub type Response = http::Response<Vec<u8>>;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TransportError {
+    // The request may or may not have reached the exchange, idempotent calls are retried.
+    Transient(String),
+    // The request was never sent and sending it again cannot help, e.g. a replay mismatch.
+    Rejected(String),
+}
+
+impl std::fmt::Display for TransportError {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TransportError::Transient(error) => write!(formatter, "{}", error),
+            TransportError::Rejected(error) => write!(formatter, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for TransportError {}
+
+/// Plain messages are transient, as every transport error used to be.
+impl From<String> for TransportError {
+    fn from(error: String) -> TransportError {
+        TransportError::Transient(error)
+    }
+}
+
+impl From<TransportError> for String {
+    fn from(error: TransportError) -> String {
+        error.to_string()
+    }
+}
+
+/// Sends fully built requests to Kuna. A `TransportError::Transient` means the request
+/// may or may not have reached the exchange, `TransportError::Rejected` fails the call at once.
+///
+/// `hyper::Client` implements it when the `hyper` feature is enabled, so an
+/// `Arc<hyper::Client<_>>` can be passed wherever an `Arc<dyn Transport>` is expected.
+pub trait Transport: Send + Sync {
+    fn send(&self, request: Request) -> BoxFuture<'_, Result<Response, TransportError>>;
+}
+
+impl<T: Transport + ?Sized> Transport for std::sync::Arc<T> {
+    fn send(&self, request: Request) -> BoxFuture<'_, Result<Response, TransportError>> {
+        (**self).send(request)
+    }
+}
+
+/// Adapts a closure into a `Transport`, handy as a test double. Closures may return
+/// either `String` or `TransportError` errors.
+pub struct FnTransport<F> {
+    handler: F,
+}
+
+impl<F, E> FnTransport<F>
+where
+    F: Fn(Request) -> Result<Response, E> + Send + Sync,
+    E: Into<TransportError>,
+{
+    pub fn new(handler: F) -> FnTransport<F> {
+        FnTransport { handler }
+    }
+}
+
+impl<F, E> Transport for FnTransport<F>
+where
+    F: Fn(Request) -> Result<Response, E> + Send + Sync,
+    E: Into<TransportError>,
+{
+    fn send(&self, request: Request) -> BoxFuture<'_, Result<Response, TransportError>> {
+        let response = (self.handler)(request).map_err(Into::into);
+        Box::pin(async move { response })
+    }
+}
+
+#[cfg(feature = "hyper")]
+impl<TConnector> Transport for hyper::Client<TConnector>
+where
+    TConnector: hyper::client::connect::Connect + Send + Sync + Clone + 'static,
+{
+    fn send(&self, request: Request) -> BoxFuture<'_, Result<Response, TransportError>> {
+        let (parts, body) = request.into_parts();
+        let request = http::Request::from_parts(parts, hyper::Body::from(body));
+        Box::pin(async move {
+            let (parts, body) = match self.request(request).await {
+                Ok(response) => response.into_parts(),
+                Err(error) => return Err(format!("Failed to create response: {:#?}", error).into()),
+            };
+            match hyper::body::to_bytes(body).await {
+                Ok(body) => Ok(http::Response::from_parts(parts, body.to_vec())),
+                Err(error) => Err(format!("Failed to get bytes from body: {:#?}", error).into()),
+            }
+        })
+    }
+}
@@ -0,0 +1,31 @@
+#![cfg(feature = "hyper")]
+
+use kuna_sdk::builder::KunaClientBuilder;
+use kuna_sdk::builder::Tls;
+
+// TLS is part of the default features.
+#[cfg(any(feature = "rustls", feature = "native-tls"))]
+#[test]
+fn default_preset_builds() {
+    assert_ne!(Tls::default(), Tls::Disabled);
+    let clients = KunaClientBuilder::production().credentials("public", "private").build();
+    match clients {
+        Ok((_, private_client)) => assert!(private_client.is_some()),
+        Err(error) => panic!("Failed to build production clients: {}", error),
+    }
+}
+
+#[test]
+fn https_url_without_tls_fails_to_build() {
+    match KunaClientBuilder::production().tls(Tls::Disabled).build_public() {
+        Ok(_) => panic!("Built a client that can not reach an https url"),
+        Err(error) => assert!(error.contains("needs TLS"), "{}", error),
+    }
+}
+
+#[test]
+fn http_url_without_tls_builds() {
+    let base_url = url::Url::parse("http://127.0.0.1:8080").expect("Failed to parse url");
+    let built = KunaClientBuilder::production().base_url(base_url).tls(Tls::Disabled).build_public();
+    assert!(built.is_ok());
+}
@@ -0,0 +1,149 @@
+use kuna_sdk::builder::KunaClientBuilder;
+use kuna_sdk::fixture::ReplayTransport;
+use kuna_sdk::fixture::Replayer;
+use kuna_sdk::retry::RetryPolicy;
+use kuna_sdk::transport::FnTransport;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
+#[derive(Default)]
+struct Attempts(AtomicUsize);
+
+impl kuna_sdk::middleware::Middleware for Attempts {
+    fn before_send(
+        &self,
+        _endpoint: kuna_sdk::base::Endpoint,
+        builder: http::request::Builder,
+        _body: &[u8],
+    ) -> http::request::Builder {
+        self.0.fetch_add(1, Ordering::SeqCst);
+        builder
+    }
+}
+
+fn builder(transport: Arc<dyn kuna_sdk::transport::Transport>, attempts: Arc<Attempts>) -> KunaClientBuilder {
+    KunaClientBuilder::production()
+        .transport(transport)
+        .retry_policy(RetryPolicy::default().with_delays(Duration::from_millis(1), Duration::from_millis(1)))
+        .middleware(attempts)
+}
+
+#[tokio::test]
+async fn replay_mismatch_fails_without_retrying() {
+    let attempts = Arc::new(Attempts::default());
+    let transport = Arc::new(ReplayTransport::new(Arc::new(Replayer::new(Vec::new()))));
+    let public_client = builder(transport, attempts.clone()).build_public().expect("Failed to build client");
+    let error = public_client.get_markets().await.unwrap_err();
+    assert!(error.starts_with("Unmatched request: GET /v3/markets"));
+    assert_eq!(attempts.0.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn plain_transport_errors_stay_transient() {
+    let attempts = Arc::new(Attempts::default());
+    let transport = Arc::new(FnTransport::new(|_| Err("Connection reset".to_owned())));
+    let public_client = builder(transport, attempts.clone()).build_public().expect("Failed to build client");
+    assert_eq!(public_client.get_markets().await.unwrap_err(), "Connection reset");
+    assert_eq!(attempts.0.load(Ordering::SeqCst), 3);
+}
+
+#[cfg(feature = "mock")]
+mod recording {
+    use kuna_sdk::base::Side;
+    use kuna_sdk::base::Target;
+    use kuna_sdk::builder::KunaClientBuilder;
+    use kuna_sdk::builder::Tls;
+    use kuna_sdk::coin::Coin;
+    use kuna_sdk::coin::Coins;
+    use kuna_sdk::fixture::Recorder;
+    use kuna_sdk::fixture::RecordingTransport;
+    use kuna_sdk::fixture::ReplayTransport;
+    use kuna_sdk::fixture::Replayer;
+    use kuna_sdk::fixture::REDACTED;
+    use kuna_sdk::mock::MockExchange;
+    use kuna_sdk::mock::MockServer;
+    use kuna_sdk::models::CreateOrder;
+    use kuna_sdk::transport::Transport;
+    use std::sync::Arc;
+
+    const PUBLIC_KEY: &str = "recorded-public-key";
+    const PRIVATE_KEY: &str = "recorded-private-key";
+
+    // Runs the same session against whatever transport the clients get.
+    async fn session(base_url: url::Url, transport: Arc<dyn Transport>) -> (usize, Vec<(Coin, f64)>, i32) {
+        let (public_client, private_client) = KunaClientBuilder::production()
+            .base_url(base_url)
+            .tls(Tls::Disabled)
+            .transport(transport)
+            .credentials(PUBLIC_KEY, PRIVATE_KEY)
+            .build()
+            .expect("Failed to build clients");
+        let private_client = private_client.expect("Missing private client");
+        let markets = public_client.get_markets().await.expect("Failed to get markets");
+        let order = private_client
+            .create_order(CreateOrder::new(Coins::TonUsdt, Side::Buy, Target::Limit, 1.0, 2.0))
+            .await
+            .expect("Failed to place order");
+        let mut balances: Vec<_> = private_client
+            .get_balance()
+            .await
+            .expect("Failed to get balance")
+            .into_iter()
+            .map(|currency| (currency.coin, currency.available))
+            .collect();
+        balances.sort_by_key(|(coin, _)| coin.to_string());
+        (markets.len(), balances, order.id)
+    }
+
+    #[tokio::test]
+    async fn recorded_session_is_redacted_and_replays() {
+        let path = std::env::temp_dir().join(format!("kuna-fixture-{}.json", std::process::id()));
+        let exchange = MockExchange::new(PUBLIC_KEY, PRIVATE_KEY);
+        exchange.set_balance(Coin::USDT, 100.0);
+        let server = MockServer::start(exchange).await.expect("Failed to start mock server");
+        let recorder = Arc::new(Recorder::new(&path));
+        let recording = Arc::new(RecordingTransport::new(
+            Arc::new(hyper::Client::new()),
+            recorder.clone(),
+        ));
+        let recorded = session(server.url(), recording).await;
+        assert_eq!(recorded.1, vec![(Coin::USDT, 98.0)]);
+        recorder.finish().expect("Failed to write fixtures");
+        let base_url = server.url();
+        drop(server);
+
+        let file = std::fs::read_to_string(&path).expect("Failed to read fixtures");
+        assert!(!file.contains(PUBLIC_KEY));
+        assert!(!file.contains(PRIVATE_KEY));
+        let replayer = Arc::new(Replayer::load(&path).expect("Failed to load fixtures"));
+        std::fs::remove_file(&path).expect("Failed to remove fixtures");
+        let interactions = replayer.unused();
+        assert_eq!(interactions.len(), 3);
+        for interaction in interactions.iter().filter(|interaction| interaction.method == "POST") {
+            for header in ["kun-apikey", "kun-signature", "kun-nonce"] {
+                assert_eq!(interaction.request_headers.get(header).map(String::as_str), Some(REDACTED), "{}", header);
+            }
+        }
+
+        let replayed = session(base_url, Arc::new(ReplayTransport::new(replayer.clone()))).await;
+        assert_eq!(replayed, recorded);
+        assert!(replayer.unused().is_empty());
+    }
+
+    #[tokio::test]
+    async fn recorder_writes_the_file_once_when_dropped() {
+        let path = std::env::temp_dir().join(format!("kuna-fixture-drop-{}.json", std::process::id()));
+        let recorder = Recorder::new(&path);
+        let request = http::Request::get("/v3/markets").body(Vec::new()).expect("Invalid request");
+        let response = http::Response::new(b"[]".to_vec());
+        recorder.record(&request, &response);
+        recorder.record(&request, &response);
+        assert!(!path.exists());
+        drop(recorder);
+        let replayer = Replayer::load(&path).expect("Failed to load fixtures");
+        std::fs::remove_file(&path).expect("Failed to remove fixtures");
+        assert_eq!(replayer.unused().len(), 2);
+    }
+}
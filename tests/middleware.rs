@@ -0,0 +1,87 @@
+use kuna_sdk::base::Endpoint;
+use kuna_sdk::builder::KunaClientBuilder;
+use kuna_sdk::middleware::HeaderMiddleware;
+use kuna_sdk::middleware::Middleware;
+use kuna_sdk::transport::FnTransport;
+use kuna_sdk::transport::Request;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+// Shared log of the hooks that ran, in order.
+type Log = Arc<Mutex<Vec<String>>>;
+
+struct Logged {
+    name: &'static str,
+    log: Log,
+}
+
+impl Middleware for Logged {
+    fn before_send(&self, _endpoint: Endpoint, builder: http::request::Builder, _body: &[u8]) -> http::request::Builder {
+        self.log.lock().expect("Log lock is poisoned").push(format!("{} before_send", self.name));
+        builder.header("x-trace", self.name)
+    }
+
+    fn after_receive(&self, _endpoint: Endpoint, _status: http::StatusCode, _headers: &http::HeaderMap, _body: &[u8]) {
+        self.log.lock().expect("Log lock is poisoned").push(format!("{} after_receive", self.name));
+    }
+}
+
+// Builds clients whose transport keeps every request it is sent and answers with an empty list.
+fn builder(requests: Arc<Mutex<Vec<Request>>>) -> KunaClientBuilder {
+    let transport = FnTransport::new(move |request: Request| {
+        requests.lock().expect("Requests lock is poisoned").push(request);
+        http::Response::builder()
+            .status(http::StatusCode::OK)
+            .body(b"[]".to_vec())
+            .map_err(|error| format!("{:#?}", error))
+    });
+    KunaClientBuilder::production().transport(Arc::new(transport))
+}
+
+#[tokio::test]
+async fn both_hooks_run_in_registration_order() {
+    let log = Log::default();
+    let requests = Arc::default();
+    let public_client = builder(Arc::clone(&requests))
+        .middleware(Arc::new(Logged { name: "first", log: log.clone() }))
+        .middleware(Arc::new(Logged { name: "second", log: log.clone() }))
+        .build_public()
+        .expect("Failed to build client");
+    public_client.get_markets().await.expect("Failed to get markets");
+
+    assert_eq!(
+        *log.lock().expect("Log lock is poisoned"),
+        vec!["first before_send", "second before_send", "first after_receive", "second after_receive"]
+    );
+    let requests = requests.lock().expect("Requests lock is poisoned");
+    let traces: Vec<_> = requests[0].headers().get_all("x-trace").iter().collect();
+    assert_eq!(traces, vec!["first", "second"]);
+}
+
+#[tokio::test]
+async fn headers_set_by_middleware_reach_the_transport_of_signed_requests() {
+    let requests = Arc::default();
+    let private_client = builder(Arc::clone(&requests))
+        .middleware(Arc::new(HeaderMiddleware::new(
+            http::header::CONTENT_TYPE,
+            http::HeaderValue::from_static("application/vnd.kuna+json"),
+        )))
+        .middleware(Arc::new(HeaderMiddleware::new(
+            http::header::HeaderName::from_static("x-client"),
+            http::HeaderValue::from_static("bot-7"),
+        )))
+        .credentials("public", "private")
+        .build_private()
+        .expect("Failed to build client");
+    private_client.get_balance().await.expect("Failed to get balance");
+
+    let requests = requests.lock().expect("Requests lock is poisoned");
+    let headers = requests[0].headers();
+    // Replaces the value set by the client instead of adding another one.
+    assert_eq!(
+        headers.get_all(http::header::CONTENT_TYPE).iter().collect::<Vec<_>>(),
+        vec!["application/vnd.kuna+json"]
+    );
+    assert_eq!(headers["x-client"], "bot-7");
+    assert!(headers.contains_key("kun-signature"));
+}
@@ -0,0 +1,537 @@
+#![cfg(feature = "mock")]
+
+use kuna_sdk::base::Endpoint;
+use kuna_sdk::base::Side;
+use kuna_sdk::base::Target;
+use kuna_sdk::builder::KunaClientBuilder;
+use kuna_sdk::builder::Tls;
+use kuna_sdk::client::KunaClient;
+use kuna_sdk::coin::Coin;
+use kuna_sdk::coin::Coins;
+use kuna_sdk::mock::MockExchange;
+use kuna_sdk::mock::MockServer;
+use kuna_sdk::models::CancelOrderRequest;
+use kuna_sdk::models::CreateOrder;
+use kuna_sdk::order::OrderStatus;
+use kuna_sdk::public::KunaPublicClient;
+use kuna_sdk::retry::RetryPolicy;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+
+const PUBLIC_KEY: &str = "public";
+const PRIVATE_KEY: &str = "private";
+const EPSILON: f64 = 1e-9;
+
+fn fast_retries() -> RetryPolicy {
+    RetryPolicy::default().with_delays(Duration::from_millis(1), Duration::from_millis(5))
+}
+
+async fn start() -> MockServer {
+    let exchange = MockExchange::new(PUBLIC_KEY, PRIVATE_KEY);
+    exchange.set_balance(Coin::USDT, 100.0);
+    exchange.set_balance(Coin::TON, 10.0);
+    MockServer::start(exchange).await.expect("Failed to start mock server")
+}
+
+fn builder(server: &MockServer) -> KunaClientBuilder {
+    KunaClientBuilder::production()
+        .base_url(server.url())
+        .tls(Tls::Disabled)
+        .retry_policy(fast_retries())
+}
+
+fn clients(server: &MockServer) -> (KunaPublicClient, KunaClient) {
+    let (public_client, private_client) = builder(server)
+        .credentials(PUBLIC_KEY, PRIVATE_KEY)
+        .build()
+        .expect("Failed to build clients");
+    (public_client, private_client.expect("Missing private client"))
+}
+
+async fn available(client: &KunaClient, coin: Coin) -> (f64, f64) {
+    let balances = client.get_balance().await.expect("Failed to get balance");
+    match balances.into_iter().find(|currency| currency.coin == coin) {
+        Some(currency) => (currency.full, currency.available),
+        None => (0.0, 0.0),
+    }
+}
+
+#[tokio::test]
+async fn public_endpoints_serve_markets_and_book() {
+    let server = start().await;
+    server.exchange().add_market(kuna_sdk::models::Market {
+        id: "tonusdt".to_owned(),
+        base_unit: "ton".to_owned(),
+        quote_unit: "usdt".to_owned(),
+        base_precision: 2,
+        quote_precision: 4,
+        display_precision: 4,
+        price_change: 1.5,
+    });
+    server.exchange().add_liquidity(Coins::TonUsdt, Side::Buy, 1.9, 3.0);
+    server.exchange().add_liquidity(Coins::TonUsdt, Side::Buy, 1.9, 1.0);
+    server.exchange().add_liquidity(Coins::TonUsdt, Side::Sell, 2.1, 2.0);
+    let (public_client, _) = clients(&server);
+
+    let markets = public_client.get_markets().await.expect("Failed to get markets");
+    assert_eq!(markets.len(), 1);
+    assert_eq!(markets[0].id, "tonusdt");
+    assert_eq!(markets[0].quote_precision, 4);
+
+    let book = public_client.get_orderbook(Coins::TonUsdt).await.expect("Failed to get book");
+    let bid = book.best_bid().expect("Missing bid");
+    assert_eq!((bid.price, bid.amount, bid.orders_number), (1.9, 4.0, 2));
+    let ask = book.best_ask().expect("Missing ask");
+    assert_eq!((ask.price, ask.amount, ask.orders_number), (2.1, 2.0, 1));
+}
+
+#[tokio::test]
+async fn limit_order_reserves_balance_until_canceled() {
+    let server = start().await;
+    let (_, client) = clients(&server);
+
+    let order = client
+        .create_order(CreateOrder::new(Coins::TonUsdt, Side::Buy, Target::Limit, 10.0, 2.0))
+        .await
+        .expect("Failed to place order");
+    assert_eq!(order.status, OrderStatus::Active);
+    assert_eq!(order.side, Side::Buy);
+    assert_eq!(order.price, Some(2.0));
+    assert_eq!(order.remaining_amount, 10.0);
+    assert_eq!(available(&client, Coin::USDT).await, (100.0, 80.0));
+
+    let open_orders = client.get_my_orders(Coins::TonUsdt).await.expect("Failed to get orders");
+    assert_eq!(open_orders.iter().map(|order| order.id).collect::<Vec<_>>(), vec![order.id]);
+
+    let canceled = client
+        .delete_order(CancelOrderRequest { order_id: order.id })
+        .await
+        .expect("Failed to cancel order");
+    assert_eq!(canceled.id, order.id);
+    assert_eq!(canceled.status, OrderStatus::Canceled);
+    assert_eq!(available(&client, Coin::USDT).await, (100.0, 100.0));
+    assert!(client.get_my_orders(Coins::TonUsdt).await.expect("Failed to get orders").is_empty());
+    let history = client.get_order_history(Coins::TonUsdt).await.expect("Failed to get history");
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0].status, OrderStatus::Canceled);
+}
+
+#[tokio::test]
+async fn order_exceeding_available_balance_is_rejected() {
+    let server = start().await;
+    let (_, client) = clients(&server);
+    client
+        .create_order(CreateOrder::new(Coins::TonUsdt, Side::Buy, Target::Limit, 30.0, 2.0))
+        .await
+        .expect("Failed to place order");
+
+    let rejected = client
+        .create_order(CreateOrder::new(Coins::TonUsdt, Side::Buy, Target::Limit, 30.0, 2.0))
+        .await;
+    assert!(rejected.is_err());
+    let rejected = client
+        .create_order(CreateOrder::new(Coins::TonUsdt, Side::Sell, Target::Limit, 11.0, 2.0))
+        .await;
+    assert!(rejected.is_err());
+    assert_eq!(server.exchange().orders().len(), 1);
+}
+
+#[tokio::test]
+async fn market_order_sweeps_resting_liquidity_in_price_order() {
+    let server = start().await;
+    server.exchange().add_liquidity(Coins::TonUsdt, Side::Sell, 2.1, 2.0);
+    server.exchange().add_liquidity(Coins::TonUsdt, Side::Sell, 2.0, 1.0);
+    let (public_client, client) = clients(&server);
+
+    let order = client
+        .create_order(CreateOrder::new(Coins::TonUsdt, Side::Buy, Target::Market, 2.0, 0.0))
+        .await
+        .expect("Failed to place order");
+    assert_eq!(order.status, OrderStatus::Executed);
+    assert_eq!(order.executed_amount, 2.0);
+    assert!((order.average_price.expect("Missing average price") - 2.05).abs() < EPSILON);
+
+    let (ton, _) = available(&client, Coin::TON).await;
+    let (usdt, _) = available(&client, Coin::USDT).await;
+    assert!((ton - 12.0).abs() < EPSILON);
+    assert!((usdt - 95.9).abs() < EPSILON);
+
+    let book = public_client.get_orderbook(Coins::TonUsdt).await.expect("Failed to get book");
+    let ask = book.best_ask().expect("Missing ask");
+    assert_eq!((ask.price, ask.amount), (2.1, 1.0));
+    assert!(client.get_my_orders(Coins::TonUsdt).await.expect("Failed to get orders").is_empty());
+    let history = client.get_order_history(Coins::TonUsdt).await.expect("Failed to get history");
+    assert_eq!(history.iter().map(|order| order.id).collect::<Vec<_>>(), vec![order.id]);
+}
+
+#[tokio::test]
+async fn marketable_limit_order_fills_partially_and_rests() {
+    let server = start().await;
+    server.exchange().add_liquidity(Coins::TonUsdt, Side::Buy, 2.0, 1.0);
+    let (_, client) = clients(&server);
+
+    let order = client
+        .create_order(CreateOrder::new(Coins::TonUsdt, Side::Sell, Target::Limit, 3.0, 1.9))
+        .await
+        .expect("Failed to place order");
+    assert_eq!(order.status, OrderStatus::PartiallyFilled);
+    assert_eq!(order.executed_amount, 1.0);
+    assert_eq!(order.remaining_amount, 2.0);
+    assert_eq!(available(&client, Coin::TON).await, (9.0, 7.0));
+}
+
+#[derive(Default)]
+struct Statuses(Mutex<Vec<(http::StatusCode, String)>>);
+
+impl kuna_sdk::middleware::Middleware for Statuses {
+    fn after_receive(
+        &self,
+        _endpoint: kuna_sdk::base::Endpoint,
+        status: http::StatusCode,
+        _headers: &http::HeaderMap,
+        body: &[u8],
+    ) {
+        let body = String::from_utf8_lossy(body).into_owned();
+        self.0.lock().expect("Statuses lock is poisoned").push((status, body));
+    }
+}
+
+#[tokio::test]
+async fn requests_with_wrong_keys_are_rejected() {
+    let server = start().await;
+    let statuses = Arc::new(Statuses::default());
+    let wrong_private_key = builder(&server)
+        .middleware(statuses.clone())
+        .credentials(PUBLIC_KEY, "another-private-key")
+        .build_private()
+        .expect("Failed to build client");
+    assert!(wrong_private_key.get_balance().await.is_err());
+    assert!(wrong_private_key
+        .create_order(CreateOrder::new(Coins::TonUsdt, Side::Buy, Target::Limit, 1.0, 2.0))
+        .await
+        .is_err());
+
+    let wrong_public_key = builder(&server)
+        .middleware(statuses.clone())
+        .credentials("another-public-key", PRIVATE_KEY)
+        .build_private()
+        .expect("Failed to build client");
+    assert!(wrong_public_key.get_balance().await.is_err());
+    assert!(server.exchange().orders().is_empty());
+
+    let statuses = statuses.0.lock().expect("Statuses lock is poisoned");
+    assert_eq!(statuses.len(), 3);
+    assert!(statuses.iter().all(|(status, _)| *status == http::StatusCode::UNAUTHORIZED));
+    assert!(statuses[0].1.contains("signature_invalid"));
+    assert!(statuses[1].1.contains("signature_invalid"));
+    assert!(statuses[2].1.contains("invalid_api_key"));
+}
+
+#[tokio::test]
+async fn server_errors_are_retried_for_idempotent_endpoints_only() {
+    let server = start().await;
+    let (_, client) = clients(&server);
+
+    server.exchange().fail_next(http::StatusCode::BAD_GATEWAY, "bad gateway");
+    let balance = client.get_balance_with_metadata().await.expect("Failed to get balance");
+    assert_eq!(balance.metadata.attempts, 2);
+
+    server.exchange().fail_next(http::StatusCode::BAD_GATEWAY, "bad gateway");
+    let submitted = client_without_reconciliation(&server)
+        .create_order(CreateOrder::new(Coins::TonUsdt, Side::Buy, Target::Limit, 1.0, 2.0))
+        .await;
+    assert!(submitted.is_err());
+    assert!(server.exchange().orders().is_empty());
+
+    let no_retries = builder(&server)
+        .credentials(PUBLIC_KEY, PRIVATE_KEY)
+        .retry_policy(RetryPolicy::none())
+        .build_private()
+        .expect("Failed to build client");
+    server.exchange().fail_next(http::StatusCode::BAD_GATEWAY, "bad gateway");
+    assert!(no_retries.get_balance().await.is_err());
+}
+
+const SUBMIT_PATH: &str = "/v3/auth/w/order/submit";
+
+fn client_without_reconciliation(server: &MockServer) -> KunaClient {
+    builder(server)
+        .credentials(PUBLIC_KEY, PRIVATE_KEY)
+        .retry_policy(fast_retries().with_order_reconciliation(false))
+        .build_private()
+        .expect("Failed to build client")
+}
+
+// Counts the requests sent to each endpoint.
+#[derive(Default)]
+struct Requests(Mutex<Vec<Endpoint>>);
+
+impl kuna_sdk::middleware::Middleware for Requests {
+    fn before_send(&self, endpoint: Endpoint, builder: http::request::Builder, _body: &[u8]) -> http::request::Builder {
+        self.0.lock().expect("Requests lock is poisoned").push(endpoint);
+        builder
+    }
+}
+
+#[tokio::test]
+async fn successful_submission_sends_no_reconciliation_queries() {
+    let server = start().await;
+    let requests = Arc::new(Requests::default());
+    let client = builder(&server)
+        .credentials(PUBLIC_KEY, PRIVATE_KEY)
+        .middleware(requests.clone())
+        .build_private()
+        .expect("Failed to build client");
+    client
+        .create_order(CreateOrder::new(Coins::TonUsdt, Side::Buy, Target::Limit, 1.0, 2.0))
+        .await
+        .expect("Failed to place order");
+    assert_eq!(*requests.0.lock().expect("Requests lock is poisoned"), vec![Endpoint::CreateOrder]);
+}
+
+#[tokio::test]
+async fn lost_submission_is_not_resent_without_reconciliation() {
+    let server = start().await;
+    let client = client_without_reconciliation(&server);
+    server
+        .exchange()
+        .lose_next_response_on(SUBMIT_PATH, http::StatusCode::BAD_GATEWAY, "bad gateway");
+    let submitted = client
+        .create_order(CreateOrder::new(Coins::TonUsdt, Side::Buy, Target::Limit, 1.0, 2.0))
+        .await;
+    assert!(submitted.is_err());
+    assert_eq!(server.exchange().orders().len(), 1);
+}
+
+#[tokio::test]
+async fn reconciliation_finds_lost_limit_order_among_open_orders() {
+    let server = start().await;
+    let (_, client) = clients(&server);
+    client
+        .create_order(CreateOrder::new(Coins::TonUsdt, Side::Buy, Target::Limit, 1.0, 2.0))
+        .await
+        .expect("Failed to place order");
+    server
+        .exchange()
+        .lose_next_response_on(SUBMIT_PATH, http::StatusCode::BAD_GATEWAY, "bad gateway");
+    let order = client
+        .create_order(CreateOrder::new(Coins::TonUsdt, Side::Buy, Target::Limit, 1.0, 2.0))
+        .await
+        .expect("Failed to reconcile order");
+    let orders = server.exchange().orders();
+    assert_eq!(orders.len(), 2);
+    assert_eq!(order.id, orders[1].id);
+    assert_eq!(order.status, OrderStatus::Active);
+}
+
+#[tokio::test]
+async fn reconciliation_finds_lost_filled_order_in_history() {
+    let server = start().await;
+    server.exchange().add_liquidity(Coins::TonUsdt, Side::Sell, 2.0, 5.0);
+    let (_, client) = clients(&server);
+    server
+        .exchange()
+        .lose_next_response_on(SUBMIT_PATH, http::StatusCode::BAD_GATEWAY, "bad gateway");
+    let order = client
+        .create_order(CreateOrder::new(Coins::TonUsdt, Side::Buy, Target::Market, 1.0, 0.0))
+        .await
+        .expect("Failed to reconcile order");
+    assert_eq!(server.exchange().orders().len(), 1);
+    assert_eq!(order.status, OrderStatus::Executed);
+    assert_eq!(order.executed_amount, 1.0);
+}
+
+#[tokio::test]
+async fn reconciliation_resends_order_that_was_not_placed() {
+    let server = start().await;
+    let (_, client) = clients(&server);
+    server
+        .exchange()
+        .fail_next_on(SUBMIT_PATH, http::StatusCode::BAD_GATEWAY, "bad gateway");
+    let order = client
+        .create_order(CreateOrder::new(Coins::TonUsdt, Side::Buy, Target::Limit, 1.0, 2.0))
+        .await
+        .expect("Failed to resend order");
+    let orders = server.exchange().orders();
+    assert_eq!(orders.len(), 1);
+    assert_eq!(order.id, orders[0].id);
+}
+
+// Makes the open orders query fail once the order submission has failed.
+struct FailOpenOrdersAfterSubmission(Arc<MockExchange>);
+
+impl kuna_sdk::middleware::Middleware for FailOpenOrdersAfterSubmission {
+    fn after_receive(
+        &self,
+        endpoint: kuna_sdk::base::Endpoint,
+        _status: http::StatusCode,
+        _headers: &http::HeaderMap,
+        _body: &[u8],
+    ) {
+        if endpoint == kuna_sdk::base::Endpoint::CreateOrder {
+            for _ in 0..2 {
+                self.0
+                    .fail_next_on("/v3/auth/r/orders/tonusdt", http::StatusCode::BAD_GATEWAY, "bad gateway");
+            }
+        }
+    }
+}
+
+#[tokio::test]
+async fn reconciliation_does_not_resend_when_checking_fails() {
+    let server = start().await;
+    let client = builder(&server)
+        .credentials(PUBLIC_KEY, PRIVATE_KEY)
+        .retry_policy(fast_retries().with_max_attempts(2))
+        .middleware(Arc::new(FailOpenOrdersAfterSubmission(server.exchange().clone())))
+        .build_private()
+        .expect("Failed to build client");
+    server
+        .exchange()
+        .lose_next_response_on(SUBMIT_PATH, http::StatusCode::BAD_GATEWAY, "bad gateway");
+    let submitted = client
+        .create_order(CreateOrder::new(Coins::TonUsdt, Side::Buy, Target::Limit, 1.0, 2.0))
+        .await;
+    assert!(submitted.unwrap_err().contains("checking failed"));
+    assert_eq!(server.exchange().orders().len(), 1);
+}
+
+fn quota_headers(pairs: &[(&'static str, &str)]) -> http::HeaderMap {
+    let mut headers = http::HeaderMap::new();
+    for (name, value) in pairs {
+        headers.insert(*name, http::HeaderValue::from_str(value).expect("Invalid header value"));
+    }
+    headers
+}
+
+const RATE_LIMITED: &str = r#"{"messages":["rate_limited"]}"#;
+
+#[tokio::test]
+async fn rate_limited_requests_wait_for_retry_after_and_are_resent() {
+    let server = start().await;
+    let (public_client, client) = clients(&server);
+    server.exchange().fail_next_with(
+        http::StatusCode::TOO_MANY_REQUESTS,
+        quota_headers(&[("retry-after", "0.2")]),
+        RATE_LIMITED,
+    );
+    let started = std::time::Instant::now();
+    let markets = public_client.get_markets_with_metadata().await.expect("Failed to get markets");
+    assert!(started.elapsed() >= Duration::from_millis(200));
+    assert_eq!(markets.metadata.attempts, 2);
+    assert_eq!(markets.metadata.status, http::StatusCode::OK);
+
+    // A 429 was not processed, so even order submission is resent.
+    server.exchange().fail_next_with(
+        http::StatusCode::TOO_MANY_REQUESTS,
+        quota_headers(&[("retry-after", "0.05")]),
+        RATE_LIMITED,
+    );
+    client
+        .create_order(CreateOrder::new(Coins::TonUsdt, Side::Buy, Target::Limit, 1.0, 2.0))
+        .await
+        .expect("Failed to place order");
+    assert_eq!(server.exchange().orders().len(), 1);
+}
+
+#[tokio::test]
+async fn rate_limited_requests_give_up_after_max_attempts() {
+    let server = start().await;
+    let (public_client, _) = clients(&server);
+    for _ in 0..3 {
+        server.exchange().fail_next_with(
+            http::StatusCode::TOO_MANY_REQUESTS,
+            quota_headers(&[("retry-after", "0")]),
+            RATE_LIMITED,
+        );
+    }
+    let error = public_client.get_markets().await.unwrap_err();
+    assert!(error.starts_with("Rate limited"), "{}", error);
+    assert!(error.contains("rate_limited"), "{}", error);
+    // All three failures were used up, one per attempt.
+    assert!(public_client.get_markets().await.is_ok());
+}
+
+#[tokio::test]
+async fn exhausted_quota_pauses_its_endpoint_group_until_reset() {
+    let server = start().await;
+    let (public_client, client) = clients(&server);
+    server.exchange().set_response_headers(quota_headers(&[
+        ("x-ratelimit-limit", "10"),
+        ("x-ratelimit-remaining", "0"),
+        ("x-ratelimit-reset", "1"),
+    ]));
+    public_client.get_markets().await.expect("Failed to get markets");
+    let status = public_client.rate_limit_status().expect("Missing quota");
+    assert_eq!(status.limit, Some(10));
+    assert_eq!(status.remaining, Some(0));
+    assert_eq!(status.reset, Some(Duration::from_secs(1)));
+
+    let quota = public_client.quota_tracker();
+    let pause = quota.paused_for(Endpoint::Markets).expect("Public endpoints are not paused");
+    assert!(pause <= Duration::from_secs(1));
+    assert_eq!(quota.paused_for(Endpoint::Balance), None);
+    assert!(client.rate_limit_status().is_none());
+
+    server.exchange().set_response_headers(quota_headers(&[("x-ratelimit-remaining", "9")]));
+    let started = std::time::Instant::now();
+    public_client.get_markets().await.expect("Failed to get markets");
+    assert!(started.elapsed() >= pause - Duration::from_millis(50));
+    assert_eq!(quota.paused_for(Endpoint::Markets), None);
+    assert_eq!(public_client.rate_limit_status().and_then(|status| status.remaining), Some(9));
+}
+
+#[tokio::test]
+async fn extreme_nonces_are_rejected_without_panicking() {
+    let server = start().await;
+    let path = "/v3/auth/r/wallets";
+    for nonce in [i64::MIN, i64::MAX] {
+        let message = kuna_sdk::base::signing_message(path, nonce, b"");
+        let signature = kuna_sdk::signer::HmacSigner::new(PRIVATE_KEY.to_owned()).sign_message(&message);
+        let request = http::Request::post(server.url().join(path).expect("Invalid url").as_str())
+            .header("kun-apikey", PUBLIC_KEY)
+            .header("kun-nonce", nonce.to_string())
+            .header("kun-signature", signature)
+            .body(hyper::Body::empty())
+            .expect("Failed to build request");
+        let response = hyper::Client::new().request(request).await.expect("Mock server failed");
+        assert_eq!(response.status(), http::StatusCode::UNAUTHORIZED);
+    }
+    assert!(clients(&server).1.get_balance().await.is_ok());
+}
+
+#[tokio::test]
+async fn metadata_describes_the_response_of_the_last_attempt() {
+    let server = start().await;
+    let (_, client) = clients(&server);
+    server.exchange().set_latency(Duration::from_millis(20));
+    server.exchange().set_response_headers(quota_headers(&[
+        ("date", "Mon, 19 Oct 2026 12:00:00 GMT"),
+        ("x-ratelimit-limit", "600"),
+        ("x-ratelimit-remaining", "599"),
+        ("x-ratelimit-reset", "1792411260"),
+    ]));
+    server.exchange().fail_next(http::StatusCode::BAD_GATEWAY, "bad gateway");
+
+    let balance = client.get_balance_with_metadata().await.expect("Failed to get balance");
+    let metadata = &balance.metadata;
+    assert_eq!(metadata.status, http::StatusCode::OK);
+    assert_eq!(metadata.headers[http::header::CONTENT_TYPE], "application/json");
+    assert_eq!(metadata.headers["x-ratelimit-limit"], "600");
+    assert_eq!(metadata.date, chrono::TimeZone::timestamp_opt(&chrono::Utc, 1792411200, 0).single());
+    assert_eq!(metadata.rate_limit_headers.len(), 3);
+    assert_eq!(metadata.rate_limit_headers["x-ratelimit-remaining"], "599");
+    assert!(!metadata.rate_limit_headers.contains_key(http::header::DATE));
+    let rate_limit = metadata.rate_limit.expect("Missing rate limit");
+    assert_eq!(rate_limit.limit, Some(600));
+    assert_eq!(rate_limit.remaining, Some(599));
+    assert_eq!(rate_limit.reset, Some(Duration::from_secs(60)));
+    assert_eq!(rate_limit.retry_after, None);
+    let body: serde_json::Value = serde_json::from_slice(&metadata.body).expect("Invalid body");
+    assert!(body.is_array());
+    assert!(metadata.body_text().to_uppercase().contains("USDT"));
+    assert!(metadata.round_trip >= Duration::from_millis(20));
+    assert_eq!(metadata.attempts, 2);
+    assert_eq!(balance.value.len(), 2);
+}
@@ -0,0 +1,103 @@
+#![cfg(feature = "mock")]
+
+use kuna_sdk::base::Side;
+use kuna_sdk::base::Target;
+use kuna_sdk::coin::Coin;
+use kuna_sdk::coin::Coins;
+use kuna_sdk::mock::MockExchange;
+use kuna_sdk::mock::MockServer;
+use kuna_sdk::models::CreateOrder;
+use kuna_sdk::order::OrderStatus;
+use kuna_sdk::paper::PaperClient;
+
+async fn start() -> (MockServer, PaperClient) {
+    let server = MockServer::start(MockExchange::new("public", "private"))
+        .await
+        .expect("Failed to start mock server");
+    let public_client = kuna_sdk::builder::KunaClientBuilder::production()
+        .base_url(server.url())
+        .tls(kuna_sdk::builder::Tls::Disabled)
+        .build_public()
+        .expect("Failed to build client");
+    let paper_client = PaperClient::new(public_client).with_balance(Coin::USDT, 100.0);
+    (server, paper_client)
+}
+
+fn buy(amount: f64, price: f64) -> CreateOrder {
+    CreateOrder::new(Coins::TonUsdt, Side::Buy, Target::Limit, amount, price)
+}
+
+async fn ton_balance(paper_client: &PaperClient) -> f64 {
+    let balances = paper_client.get_balance().await.expect("Failed to get balance");
+    balances
+        .into_iter()
+        .find(|currency| currency.coin == Coin::TON)
+        .map_or(0.0, |currency| currency.full)
+}
+
+#[tokio::test]
+async fn liquidity_taken_on_placement_is_not_taken_again() {
+    let (server, paper_client) = start().await;
+    server.exchange().add_liquidity(Coins::TonUsdt, Side::Sell, 2.0, 1.0);
+
+    let first = paper_client.create_order(buy(1.0, 2.0)).await.expect("Failed to place order");
+    assert_eq!(first.status, OrderStatus::Executed);
+    let second = paper_client.create_order(buy(1.0, 2.0)).await.expect("Failed to place order");
+    assert_eq!(second.status, OrderStatus::Active);
+
+    let open_orders = paper_client.get_my_orders(Coins::TonUsdt).await.expect("Failed to get orders");
+    assert_eq!(open_orders.iter().map(|order| order.id).collect::<Vec<_>>(), vec![second.id]);
+    assert_eq!(ton_balance(&paper_client).await, 1.0);
+}
+
+#[tokio::test]
+async fn resting_orders_share_new_liquidity_in_placement_order() {
+    let (server, paper_client) = start().await;
+    let first = paper_client.create_order(buy(1.0, 1.9)).await.expect("Failed to place order");
+    let second = paper_client.create_order(buy(1.0, 1.9)).await.expect("Failed to place order");
+    server.exchange().add_liquidity(Coins::TonUsdt, Side::Sell, 1.9, 1.5);
+
+    let open_orders = paper_client.get_my_orders(Coins::TonUsdt).await.expect("Failed to get orders");
+    assert_eq!(open_orders.len(), 1);
+    assert_eq!(open_orders[0].id, second.id);
+    assert_eq!(open_orders[0].executed_amount, 0.5);
+    // Refreshing against the unchanged book fills nothing more.
+    let open_orders = paper_client.get_my_orders(Coins::TonUsdt).await.expect("Failed to get orders");
+    assert_eq!(open_orders[0].executed_amount, 0.5);
+    assert_eq!(ton_balance(&paper_client).await, 1.5);
+
+    let history = paper_client.get_order_history(Coins::TonUsdt).await.expect("Failed to get history");
+    assert_eq!(history.iter().map(|order| order.id).collect::<Vec<_>>(), vec![first.id]);
+}
+
+#[tokio::test]
+async fn resting_buys_reserve_the_fee() {
+    let (_server, paper_client) = start().await;
+    let paper_client = paper_client.with_fee_rate(0.01);
+    paper_client.create_order(buy(1.0, 50.0)).await.expect("Failed to place order");
+
+    let balances = paper_client.get_balance().await.expect("Failed to get balance");
+    let usdt = balances
+        .iter()
+        .find(|currency| currency.coin == Coin::USDT)
+        .expect("Missing USDT balance");
+    assert_eq!((usdt.full, usdt.available), (100.0, 49.5));
+    // 49.5 plus its fee is more than is left.
+    assert!(paper_client.create_order(buy(1.0, 49.5)).await.is_err());
+}
+
+#[tokio::test]
+async fn market_orders_have_no_limit_price() {
+    let (server, paper_client) = start().await;
+    server.exchange().add_liquidity(Coins::TonUsdt, Side::Sell, 2.0, 1.0);
+    server.exchange().add_liquidity(Coins::TonUsdt, Side::Sell, 3.0, 1.0);
+
+    let market = CreateOrder::new(Coins::TonUsdt, Side::Buy, Target::Market, 2.0, 0.0);
+    let order = paper_client.create_order(market).await.expect("Failed to place order");
+    assert_eq!(order.status, OrderStatus::Executed);
+    assert_eq!(order.price, None);
+    assert_eq!(order.average_price, Some(2.5));
+
+    let history = paper_client.get_order_history(Coins::TonUsdt).await.expect("Failed to get history");
+    assert_eq!(history[0].price, None);
+}
@@ -0,0 +1,168 @@
+use kuna_sdk::models::CanceledOrderResponse;
+use kuna_sdk::models::WalletEntry;
+use kuna_sdk::order::Order;
+use kuna_sdk::order::OrderEntry;
+use proptest::prelude::*;
+use serde_json::Value;
+use std::convert::TryFrom;
+
+// Strings the parsers look for, next to arbitrary ones.
+fn text() -> impl Strategy<Value = String> {
+    prop_oneof![
+        prop::sample::select(vec![
+            "", "tonusdt", "TONUSDT", "ton", "usdt", "exchange", "LIMIT", "market", "buy", "sell", "0", "-0", "1.5",
+            "-2.25", "1e400", "-1e400", "NaN", "inf", "0x10", "9223372036854775808", "ACTIVE", "EXECUTED @ 2.5(1.0)",
+            "PARTIALLY FILLED @ 2.6(0.75)", "CANCELED was: PARTIALLY FILLED", "canceled", "REJECTED",
+        ])
+        .prop_map(|text| text.to_owned()),
+        "\\PC{0,12}",
+    ]
+}
+
+fn scalar() -> impl Strategy<Value = Value> {
+    prop_oneof![
+        Just(Value::Null),
+        any::<bool>().prop_map(Value::from),
+        any::<i64>().prop_map(Value::from),
+        any::<u64>().prop_map(Value::from),
+        prop::num::f64::NORMAL.prop_map(Value::from),
+        prop::sample::select(vec![0i64, -1, 1, 1_700_000_000_000, i32::MAX as i64 + 1, i64::MIN, i64::MAX])
+            .prop_map(Value::from),
+        text().prop_map(Value::from),
+    ]
+}
+
+fn json() -> impl Strategy<Value = Value> {
+    scalar().prop_recursive(3, 32, 8, |inner| {
+        prop_oneof![
+            prop::collection::vec(inner.clone(), 0..8).prop_map(Value::from),
+            prop::collection::btree_map(text(), inner, 0..8)
+                .prop_map(|map| Value::Object(map.into_iter().collect())),
+        ]
+    })
+}
+
+fn valid_order() -> Vec<Value> {
+    serde_json::from_str(
+        r#"[100234522, null, null, "tonusdt", 1700000001000, 1700000002500, "-0.25", "-1",
+            "LIMIT", null, null, null, null, "PARTIALLY FILLED @ 2.6(0.75)", null, null, "2.6", "2.6"]"#,
+    )
+    .expect("Failed to parse order")
+}
+
+// Either random arrays or a valid order with some positions replaced or cut off.
+fn order_values() -> impl Strategy<Value = Vec<Value>> {
+    prop_oneof![
+        prop::collection::vec(scalar(), 0..24),
+        (prop::collection::vec((0..18usize, json()), 0..6), 0..=18usize).prop_map(|(replacements, length)| {
+            let mut values = valid_order();
+            for (index, value) in replacements {
+                values[index] = value;
+            }
+            values.truncate(length.max(1));
+            values
+        }),
+    ]
+}
+
+fn wallet_values() -> impl Strategy<Value = Vec<Value>> {
+    prop_oneof![
+        prop::collection::vec(scalar(), 0..8),
+        (prop::collection::vec((0..5usize, json()), 0..4), 0..=5usize).prop_map(|(replacements, length)| {
+            let mut values: Vec<Value> = serde_json::from_str(r#"["exchange", "usdt", 100.5, null, "42.25"]"#)
+                .expect("Failed to parse wallet");
+            for (index, value) in replacements {
+                values[index] = value;
+            }
+            values.truncate(length);
+            values
+        }),
+    ]
+}
+
+// Mostly valid values so that conversions get past the first field.
+fn field(valid: &'static [&'static str]) -> impl Strategy<Value = String> {
+    prop_oneof![3 => prop::sample::select(valid).prop_map(|text| text.to_owned()), 1 => text()]
+}
+
+fn canceled_order() -> impl Strategy<Value = Value> {
+    let amounts: &'static [&'static str] = &["0", "0.25", "1", "1e400", "-2.5"];
+    (
+        prop_oneof![any::<i32>().prop_map(i64::from), any::<i64>()],
+        (
+            field(&["buy", "sell"]),
+            field(&["limit", "market"]),
+            field(amounts),
+            field(amounts),
+            field(&["canceled", "executed", "active"]),
+            field(&["tonusdt"]),
+        ),
+        (field(amounts), field(amounts), field(amounts)),
+        prop_oneof![any::<u64>(), Just(0u64), Just(1_700_000_003u64), Just(1_700_000_003_000u64), Just(u64::MAX)],
+        prop::collection::vec(prop::option::of(text()), 5),
+        prop::collection::btree_map(text(), json(), 0..3),
+    )
+        .prop_map(|(id, (side, order_type, price, average, state, symbol), amounts, timestamp, flags, extra)| {
+            let mut order: serde_json::Map<String, Value> = extra.into_iter().collect();
+            let (original, remaining, executed) = amounts;
+            let fields = [
+                ("side", side),
+                ("type", order_type),
+                ("price", price),
+                ("avg_execution_price", average),
+                ("state", state),
+                ("symbol", symbol),
+                ("original_amount", original),
+                ("remaining_amount", remaining),
+                ("executed_amount", executed),
+            ];
+            for (field, text) in fields.iter() {
+                order.insert(field.to_string(), Value::from(text.as_str()));
+            }
+            order.insert("id".to_owned(), Value::from(id));
+            order.insert("timestamp".to_owned(), Value::from(timestamp));
+            let flag_fields = ["is_cancelled", "is_hidden", "is_live", "was_forced", "exchange"];
+            for (field, flag) in flag_fields.iter().zip(flags) {
+                order.insert(field.to_string(), flag.map(Value::from).unwrap_or(Value::Null));
+            }
+            Value::Object(order)
+        })
+}
+
+proptest! {
+    #[test]
+    fn order_arrays_never_panic(values in order_values()) {
+        let entry = OrderEntry::from_values(&values);
+        if let Ok(order) = Order::try_from(values.clone()) {
+            prop_assert!(entry.is_ok());
+            prop_assert!(order.remaining_amount >= 0.0 || order.remaining_amount.is_nan());
+        }
+        let _ = serde_json::from_value::<OrderEntry>(Value::from(values));
+    }
+
+    #[test]
+    fn arbitrary_json_orders_never_panic(value in json()) {
+        if let Value::Array(values) = &value {
+            let _ = Order::try_from(values.clone());
+        }
+        let _ = serde_json::from_value::<OrderEntry>(value.clone());
+        if let Ok(order) = serde_json::from_value::<CanceledOrderResponse>(value) {
+            let _ = Order::try_from(order);
+        }
+    }
+
+    #[test]
+    fn wallet_arrays_never_panic(values in wallet_values()) {
+        let entry = WalletEntry::from_values(&values);
+        let deserialized = serde_json::from_value::<WalletEntry>(Value::from(values));
+        prop_assert_eq!(entry.is_ok(), deserialized.is_ok());
+    }
+
+    #[test]
+    fn canceled_orders_never_panic(value in canceled_order()) {
+        let order = serde_json::from_value::<CanceledOrderResponse>(value);
+        if let Ok(order) = order {
+            let _ = Order::try_from(order);
+        }
+    }
+}
@@ -0,0 +1,106 @@
+use kuna_sdk::base::Side;
+use kuna_sdk::base::Target;
+use kuna_sdk::coin::Coin;
+use kuna_sdk::coin::Coins;
+use kuna_sdk::order::Order;
+use kuna_sdk::order::OrderStatus;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+fn round_trip<T: Serialize + DeserializeOwned>(value: &T) -> (serde_json::Value, T) {
+    let json = serde_json::to_value(value).expect("Failed to serialize");
+    let value = serde_json::from_value(json.clone()).expect("Failed to deserialize");
+    (json, value)
+}
+
+// For types without `PartialEq`, the json of the round-tripped value must not change.
+fn assert_stable<T: Serialize + DeserializeOwned>(value: &T) -> serde_json::Value {
+    let (json, value) = round_trip(value);
+    assert_eq!(serde_json::to_value(&value).expect("Failed to serialize"), json);
+    json
+}
+
+#[test]
+fn unknown_coin_with_a_known_name_reads_back_as_that_coin() {
+    let (json, coin) = round_trip(&Coin::Unknown("TON".to_owned()));
+    assert_eq!(json, "TON");
+    assert_eq!(coin, Coin::TON);
+    assert_eq!(Coin::from("TON"), Coin::TON);
+}
+
+#[test]
+fn coins_round_trip() {
+    let coins = [
+        (Coin::TON, "ton"),
+        (Coin::USDT, "usdt"),
+        (Coin::Unknown("doge".to_owned()), "doge"),
+        (Coin::Unknown("DOGE".to_owned()), "DOGE"),
+    ];
+    for (coin, name) in coins.iter() {
+        assert_eq!(round_trip(coin), (serde_json::json!(name), coin.clone()));
+    }
+    assert_eq!(round_trip(&Coins::TonUsdt), (serde_json::json!("tonusdt"), Coins::TonUsdt));
+    assert!(serde_json::from_value::<Coins>(serde_json::json!("btcusdt")).is_err());
+}
+
+#[test]
+fn sides_targets_and_statuses_round_trip() {
+    assert_eq!(round_trip(&Side::Buy), (serde_json::json!("buy"), Side::Buy));
+    assert_eq!(round_trip(&Side::Sell), (serde_json::json!("sell"), Side::Sell));
+    assert_eq!(round_trip(&Target::Limit), (serde_json::json!("limit"), Target::Limit));
+    assert_eq!(round_trip(&Target::Market), (serde_json::json!("market"), Target::Market));
+    let statuses = [
+        (OrderStatus::Active, serde_json::json!("active")),
+        (OrderStatus::PartiallyFilled, serde_json::json!("partially_filled")),
+        (OrderStatus::Executed, serde_json::json!("executed")),
+        (OrderStatus::Canceled, serde_json::json!("canceled")),
+        (OrderStatus::PartiallyCanceled, serde_json::json!("partially_canceled")),
+        (OrderStatus::Rejected, serde_json::json!("rejected")),
+        (OrderStatus::Unknown("ON HOLD".to_owned()), serde_json::json!({ "unknown": "ON HOLD" })),
+    ];
+    for (status, json) in statuses.iter() {
+        assert_eq!(&round_trip(status), &(json.clone(), status.clone()));
+    }
+}
+
+#[test]
+fn models_round_trip() {
+    use chrono::TimeZone;
+    let order = Order {
+        id: 42,
+        coins: Coins::TonUsdt,
+        side: Side::Sell,
+        target: Target::Limit,
+        status: OrderStatus::PartiallyFilled,
+        created_at: chrono::Utc.timestamp_millis_opt(1700000000123).single(),
+        updated_at: None,
+        original_amount: 2.0,
+        remaining_amount: 0.5,
+        executed_amount: 1.5,
+        price: Some(2.5),
+        average_price: None,
+    };
+    let json = assert_stable(&order);
+    assert_eq!(json["created_at"], "2023-11-14T22:13:20.123Z");
+    assert_eq!(json["side"], "sell");
+    assert_eq!(json["coins"], "tonusdt");
+
+    let book = kuna_sdk::models::OrderBook::with(Coins::TonUsdt, vec![(2.0, 1.5, 3), (2.1, -0.5, 1)]);
+    let json = assert_stable(&book);
+    assert_eq!(json["asks"][0], serde_json::json!({ "price": 2.1, "amount": 0.5, "orders_number": 1 }));
+
+    assert_stable(&kuna_sdk::models::Currency {
+        coin: Coin::Unknown("doge".to_owned()),
+        full: 10.0,
+        available: 7.5,
+    });
+    assert_stable(&kuna_sdk::models::Market {
+        id: "tonusdt".to_owned(),
+        base_unit: "ton".to_owned(),
+        quote_unit: "usdt".to_owned(),
+        base_precision: 2,
+        quote_precision: 4,
+        display_precision: 4,
+        price_change: -1.25,
+    });
+}